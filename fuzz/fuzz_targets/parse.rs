@@ -0,0 +1,42 @@
+#![no_main]
+
+use fc::{cartridge, cpu, display, ines, ppu};
+use libfuzzer_sys::fuzz_target;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+// any input must end in Ok or Err, never in a panic
+fuzz_target!(|data: &[u8]| {
+    let Ok(ines) = ines::INes::parse(data) else {
+        return;
+    };
+    let _ = ines.to_string();
+    let Ok(cartridge) = cartridge::load(&ines) else {
+        return;
+    };
+
+    // the same wiring as main
+    let display = Rc::new(RefCell::new(display::Display::default()));
+    let ppu_memory = ppu::MemoryMap::new(Rc::clone(&cartridge));
+    let ppu = Rc::new(RefCell::new(ppu::PPU::new(
+        RefCell::new(ppu::Register::default()),
+        ppu_memory,
+        display,
+    )));
+    let cpu_memory = cpu::MemoryMap::new(
+        Rc::clone(&ppu),
+        Rc::clone(&cartridge),
+        vec![0; 0x2000],
+        vec![0; 0x401F - 0x4000],
+    );
+    let mut cpu = cpu::CPU::new(cpu::Register::default(), cpu_memory);
+    let _ = cpu.reset();
+
+    for i in 0x4020..=0xFFFF {
+        let _ = cartridge.borrow().get(i);
+    }
+    for i in 0x0000..0x2000 {
+        let _ = cartridge.borrow().character(i);
+        let _ = cartridge.borrow().sprite(i);
+    }
+});
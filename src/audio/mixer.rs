@@ -0,0 +1,57 @@
+pub const SAMPLE_RATE: usize = 44100;
+pub const NTSC_CPU_CLOCK: usize = 1_789_773;
+pub const PAL_CPU_CLOCK: usize = 1_662_607;
+pub const DENDY_CPU_CLOCK: usize = 1_773_448;
+
+/// mixes the sound sources and downsamples them from the CPU clock to SAMPLE_RATE.
+#[derive(Debug)]
+pub struct Mixer {
+    cpu_clock: usize,
+    sum: f32,
+    count: usize,
+    phase: usize,
+    samples: Vec<f32>,
+}
+
+impl Mixer {
+    pub fn new(cpu_clock: usize) -> Self {
+        Self {
+            cpu_clock,
+            sum: 0.0,
+            count: 0,
+            phase: 0,
+            samples: vec![],
+        }
+    }
+
+    /// output of one CPU cycle.
+    /// `expansion` is the sound of the cartridge.
+    pub fn push(&mut self, expansion: f32) {
+        self.sum += expansion;
+        self.count += 1;
+        self.phase += SAMPLE_RATE;
+        if self.phase >= self.cpu_clock {
+            self.phase -= self.cpu_clock;
+            self.samples.push(self.sum / self.count as f32);
+            self.sum = 0.0;
+            self.count = 0;
+        }
+    }
+
+    /// take the samples mixed so far
+    pub fn drain(&mut self) -> Vec<f32> {
+        std::mem::take(&mut self.samples)
+    }
+}
+
+#[test]
+fn it_downsample() {
+    let mut mixer = Mixer::new(PAL_CPU_CLOCK);
+    for _ in 0..PAL_CPU_CLOCK {
+        mixer.push(0.5);
+    }
+    let samples = mixer.drain();
+    assert_eq!(samples.len(), SAMPLE_RATE);
+    assert!(samples.iter().all(|v| *v == 0.5));
+    assert!(mixer.drain().is_empty());
+}
@@ -0,0 +1,5 @@
+mod mixer;
+mod wav;
+
+pub use mixer::{Mixer, DENDY_CPU_CLOCK, NTSC_CPU_CLOCK, PAL_CPU_CLOCK};
+pub use wav::WavWriter;
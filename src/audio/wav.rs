@@ -0,0 +1,80 @@
+use crate::result::Result;
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use super::mixer::SAMPLE_RATE;
+
+/// size of the RIFF header before the samples
+const HEADER_LENGTH: u32 = 44;
+
+/// 16 bit mono WAV of the mixed samples.
+/// the sizes in the header are written by `finish`.
+pub struct WavWriter<W: Write + Seek> {
+    writer: W,
+    length: u32,
+}
+
+impl WavWriter<BufWriter<File>> {
+    pub fn create(path: &Path) -> Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    pub fn new(mut writer: W) -> Result<Self> {
+        writer.write_all(&header(0))?;
+        Ok(Self { writer, length: 0 })
+    }
+
+    /// samples of 0.0～1.0
+    pub fn write(&mut self, samples: &[f32]) -> Result<()> {
+        for v in samples {
+            let v = (v.clamp(0.0, 1.0) * i16::MAX as f32) as i16;
+            self.writer.write_all(&v.to_le_bytes())?;
+        }
+        self.length += samples.len() as u32 * 2;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<W> {
+        self.writer.seek(SeekFrom::Start(0))?;
+        self.writer.write_all(&header(self.length))?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// RIFF header of `length` bytes of samples
+fn header(length: u32) -> [u8; HEADER_LENGTH as usize] {
+    let rate = SAMPLE_RATE as u32;
+    let mut header = [0; HEADER_LENGTH as usize];
+    header[0..4].copy_from_slice(b"RIFF");
+    header[4..8].copy_from_slice(&(HEADER_LENGTH - 8 + length).to_le_bytes());
+    header[8..16].copy_from_slice(b"WAVEfmt ");
+    header[16..20].copy_from_slice(&16u32.to_le_bytes());
+    // PCM, 1 channel
+    header[20..22].copy_from_slice(&1u16.to_le_bytes());
+    header[22..24].copy_from_slice(&1u16.to_le_bytes());
+    header[24..28].copy_from_slice(&rate.to_le_bytes());
+    header[28..32].copy_from_slice(&(rate * 2).to_le_bytes());
+    // 2 bytes a frame, 16 bits a sample
+    header[32..34].copy_from_slice(&2u16.to_le_bytes());
+    header[34..36].copy_from_slice(&16u16.to_le_bytes());
+    header[36..40].copy_from_slice(b"data");
+    header[40..44].copy_from_slice(&length.to_le_bytes());
+    header
+}
+
+#[test]
+fn it_write() {
+    let mut wav = WavWriter::new(std::io::Cursor::new(vec![])).unwrap();
+    wav.write(&[0.0, 1.0]).unwrap();
+    wav.write(&[0.5]).unwrap();
+    let data = wav.finish().unwrap().into_inner();
+    assert_eq!(data.len(), 44 + 6);
+    assert_eq!(&data[0..4], b"RIFF");
+    assert_eq!(u32::from_le_bytes(data[4..8].try_into().unwrap()), 36 + 6);
+    assert_eq!(u32::from_le_bytes(data[40..44].try_into().unwrap()), 6);
+    assert_eq!(&data[44..48], &[0x00, 0x00, 0xFF, 0x7F]);
+}
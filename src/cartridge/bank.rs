@@ -0,0 +1,24 @@
+/// offset of the `n`th bank of `size` bytes.
+/// bank numbers larger than the memory wrap around like the unconnected address lines.
+pub fn offset(len: usize, size: usize, n: usize) -> usize {
+    let count = std::cmp::max(len / size, 1);
+    (n % count) * size
+}
+
+/// offset of the `n`th bank counted from the end of the memory
+pub fn last(len: usize, size: usize, n: usize) -> usize {
+    let count = std::cmp::max(len / size, 1);
+    offset(len, size, count - 1 - (n % count))
+}
+
+#[test]
+fn it_offset() {
+    assert_eq!(offset(0x8000, 0x2000, 1), 0x2000);
+    assert_eq!(offset(0x8000, 0x2000, 5), 0x2000);
+}
+
+#[test]
+fn it_last() {
+    assert_eq!(last(0x8000, 0x2000, 0), 0x6000);
+    assert_eq!(last(0x8000, 0x2000, 2), 0x2000);
+}
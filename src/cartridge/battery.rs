@@ -0,0 +1,15 @@
+/// copies the saved data to the battery-backed RAM.
+/// a save of another size fills what fits, the rest is kept.
+pub fn restore(ram: &mut [u8], data: &[u8]) {
+    let n = data.len().min(ram.len());
+    ram[..n].copy_from_slice(&data[..n]);
+}
+
+#[test]
+fn it_restore() {
+    let mut ram = [0; 4];
+    restore(&mut ram, &[1, 2]);
+    assert_eq!(ram, [1, 2, 0, 0]);
+    restore(&mut ram, &[3, 4, 5, 6, 7]);
+    assert_eq!(ram, [3, 4, 5, 6]);
+}
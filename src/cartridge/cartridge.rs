@@ -0,0 +1,123 @@
+use super::character::Character;
+use super::fds::FDS;
+use super::fme7::FME7;
+use super::mapper::Mapper;
+use super::mmc2::{Kind, MMC2};
+use super::mmc5::MMC5;
+use super::namco163::Namco163;
+use super::nrom::NROM;
+use super::uxrom::UxROM;
+use super::vrc::{Wiring, VRC};
+use super::vrc6::VRC6;
+use crate::fds::DiskImage;
+use crate::ines::INes;
+use crate::result::Result;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// shared by the CPU bus and the PPU bus
+pub type Cartridge = Rc<RefCell<dyn Mapper>>;
+
+/// common name of the board, None for unknown numbers
+pub fn mapper_name(mapper: u16) -> Option<&'static str> {
+    Some(match mapper {
+        0 => "NROM",
+        1 => "MMC1",
+        2 => "UxROM",
+        3 => "CNROM",
+        4 => "MMC3",
+        5 => "MMC5",
+        7 => "AxROM",
+        9 => "MMC2",
+        10 => "MMC4",
+        11 => "Color Dreams",
+        13 => "CPROM",
+        16 => "Bandai FCG",
+        18 => "Jaleco SS88006",
+        19 => "Namco 163",
+        21 => "VRC4a/VRC4c",
+        22 => "VRC2a",
+        23 => "VRC2b/VRC4e",
+        24 => "VRC6a",
+        25 => "VRC4b/VRC4d",
+        26 => "VRC6b",
+        34 => "BNROM/NINA-001",
+        64 => "RAMBO-1",
+        66 => "GxROM",
+        69 => "Sunsoft FME-7",
+        71 => "Camerica",
+        73 => "VRC3",
+        75 => "VRC1",
+        85 => "VRC7",
+        206 => "Namco 108",
+        _ => return None,
+    })
+}
+
+pub fn load(ines: &INes) -> Result<Cartridge> {
+    let header = ines.header();
+    let program = ines.program();
+    let character = if header.character_ram() {
+        Character::ram(header.character_ram_size())
+    } else {
+        Character::new(ines.character())
+    };
+    let battery = header.battery();
+    let cartridge: Cartridge = match header.mapper() {
+        0 => Rc::new(RefCell::new(NROM::new(
+            program,
+            character,
+            header.mirroring(),
+            battery,
+        ))),
+        2 => Rc::new(RefCell::new(UxROM::new(
+            program,
+            character,
+            header.mirroring(),
+        ))),
+        5 => Rc::new(RefCell::new(MMC5::new(program, character, battery))),
+        9 => Rc::new(RefCell::new(MMC2::new(
+            Kind::MMC2,
+            program,
+            character,
+            battery,
+        ))),
+        10 => Rc::new(RefCell::new(MMC2::new(
+            Kind::MMC4,
+            program,
+            character,
+            battery,
+        ))),
+        19 => Rc::new(RefCell::new(Namco163::new(program, character, battery))),
+        n @ (21 | 22 | 23 | 25) => {
+            // without a submapper (iNES) both variants of the board are wired
+            let wiring = Wiring::from(n, header.submapper()).unwrap();
+            Rc::new(RefCell::new(VRC::new(wiring, program, character, battery)))
+        }
+        24 => Rc::new(RefCell::new(VRC6::new(false, program, character, battery))),
+        26 => Rc::new(RefCell::new(VRC6::new(true, program, character, battery))),
+        69 => Rc::new(RefCell::new(FME7::new(program, character, battery))),
+        n => {
+            return Err(anyhow::anyhow!(
+                "unsupported mapper {} (submapper {})",
+                n,
+                header.submapper()
+            ))
+        }
+    };
+    if let Some(trainer) = ines.trainer() {
+        cartridge.borrow_mut().load_trainer(trainer);
+    }
+    Ok(cartridge)
+}
+
+/// RAM adapter with the disk in the drive, the BIOS is 8 KB at 0xE000
+pub fn load_fds(disk: &DiskImage, bios: &[u8]) -> Result<Cartridge> {
+    if bios.len() != 0x2000 {
+        return Err(anyhow::anyhow!(
+            "FDS BIOS must be 8192 bytes but it has {} bytes",
+            bios.len()
+        ));
+    }
+    Ok(Rc::new(RefCell::new(FDS::new(bios, disk.sides().to_vec()))))
+}
@@ -0,0 +1,88 @@
+use crate::ines::SpriteROM;
+use crate::memory::{ROM, WOM};
+use crate::result::{e, Result};
+use crate::sprite::Sprite;
+
+/// CHR memory on the board.
+/// it keeps both raw bytes and decoded tiles.
+#[derive(Debug)]
+pub struct Character {
+    raw: Vec<u8>,
+    sprites: SpriteROM,
+    /// CHR RAM instead of CHR ROM
+    writable: bool,
+}
+
+impl Character {
+    pub fn new(raw: &[u8]) -> Self {
+        Self {
+            raw: raw.to_vec(),
+            sprites: SpriteROM::new(raw),
+            writable: false,
+        }
+    }
+
+    /// blank CHR RAM for boards without CHR ROM
+    pub fn ram(size: usize) -> Self {
+        let raw = vec![0; size];
+        Self {
+            sprites: SpriteROM::new(&raw),
+            raw,
+            writable: true,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.raw.len()
+    }
+
+    /// decoded tile which starts at the offset
+    pub fn sprite(&self, offset: usize) -> Sprite {
+        if self.raw.is_empty() {
+            Sprite::default()
+        } else {
+            self.sprites[(offset % self.raw.len()) / 16]
+        }
+    }
+}
+
+impl ROM<usize> for Character {
+    type Output = u8;
+
+    fn get(&self, i: usize) -> Result<Self::Output> {
+        if self.raw.is_empty() {
+            Err(e::index_out_of_range(i))
+        } else {
+            Ok(self.raw[i % self.raw.len()])
+        }
+    }
+}
+
+impl WOM<usize> for Character {
+    type Input = u8;
+
+    /// the decoded tile is updated together
+    fn put(&mut self, i: usize, v: Self::Input) -> Result<()> {
+        if !self.writable || self.raw.is_empty() {
+            return Err(e::readonly(i));
+        }
+        let i = i % self.raw.len();
+        self.raw[i] = v;
+        let from = i / 16 * 16;
+        self.sprites[i / 16] = Sprite::new(&self.raw[from..from + 16].try_into().unwrap());
+        Ok(())
+    }
+}
+
+#[test]
+fn it_put() {
+    let mut rom = Character::new(&[0; 32]);
+    assert!(rom.put(0, 1).is_err());
+
+    let mut ram = Character::ram(0x2000);
+    ram.put(0x0010, 0b1000_0000).unwrap();
+    ram.put(0x0018, 0b1000_0000).unwrap();
+    assert_eq!(ram.get(0x0010).unwrap(), 0b1000_0000);
+    assert!(!ram.sprite(0x0010).zero());
+    assert!(ram.sprite(0x0000).zero());
+}
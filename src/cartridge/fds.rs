@@ -0,0 +1,503 @@
+use super::character::Character;
+use super::fds_audio::FdsAudio;
+use super::mapper::{Mapper, Mirroring};
+use crate::bits::Byte;
+use crate::fds;
+use crate::memory::{RAM, ROM, WOM};
+use crate::result::{e, Result};
+use crate::sprite::Sprite;
+use std::cell::Cell;
+
+/// CPU cycles to transfer a byte, the disk runs at 96.4 kbit/s
+const BYTE_CYCLES: usize = 150;
+/// CPU cycles from the motor start until the head reaches the first gap
+const SPIN_UP_CYCLES: usize = 50000;
+/// the disk stays out this long when the side is changed, the BIOS sees the ejection
+const INSERT_CYCLES: usize = 1_789_773 / 2;
+
+/// Famicom Disk System, the RAM adapter and the disk drive.
+/// 0x6000～0xDFFF is PRG RAM and 0xE000～0xFFFF is the BIOS.
+pub struct FDS {
+    bios: Vec<u8>,
+    ram: Vec<u8>,
+    character: Character,
+    /// sides as loaded, the written sides are compared with them
+    original: Vec<Vec<u8>>,
+    sides: Vec<Vec<u8>>,
+    /// side in the drive
+    side: Option<usize>,
+    /// side inserted when `insert` counts down to 0
+    next_side: Option<usize>,
+    insert: usize,
+
+    /// 0x4023 bit 0
+    disk_registers: bool,
+    /// 0x4023 bit 1
+    sound_registers: bool,
+
+    /// 0x4020, 0x4021
+    timer_reload: u16,
+    timer_counter: u16,
+    /// 0x4022 bit 0
+    timer_repeat: bool,
+    /// 0x4022 bit 1
+    timer_enabled: bool,
+    /// Cell because reading 0x4030 acknowledges it
+    timer_irq: Cell<bool>,
+
+    /// 0x4025 bit 0
+    motor: bool,
+    /// 0x4025 bit 1, the head stays at the start
+    transfer_reset: bool,
+    /// 0x4025 bit 2, 0: write, 1: read
+    read_mode: bool,
+    /// 0x4025 bit 3
+    mirroring: Mirroring,
+    /// 0x4025 bit 4, the CRC is written instead of the data
+    crc_control: bool,
+    previous_crc_control: bool,
+    /// 0x4025 bit 6, the transfer starts at the end of the gap
+    transfer_start: bool,
+    /// 0x4025 bit 7
+    disk_irq_enabled: bool,
+    disk_irq: Cell<bool>,
+    /// a byte has been transferred, Cell because reads acknowledge it
+    transfer_complete: Cell<bool>,
+    /// 0x4031
+    read_data: u8,
+    /// 0x4024
+    write_data: u8,
+
+    /// the head is at the end (or the start) of the disk
+    end_of_head: bool,
+    scanning: bool,
+    gap_ended: bool,
+    position: usize,
+    delay: usize,
+    crc: u16,
+
+    audio: FdsAudio,
+}
+
+impl FDS {
+    pub fn new(bios: &[u8], sides: Vec<Vec<u8>>) -> Self {
+        Self {
+            bios: bios.to_vec(),
+            ram: vec![0; 0x8000],
+            character: Character::ram(0x2000),
+            original: sides.clone(),
+            side: (!sides.is_empty()).then_some(0),
+            sides,
+            next_side: None,
+            insert: 0,
+            disk_registers: false,
+            sound_registers: false,
+            timer_reload: 0,
+            timer_counter: 0,
+            timer_repeat: false,
+            timer_enabled: false,
+            timer_irq: Cell::new(false),
+            motor: false,
+            transfer_reset: false,
+            read_mode: true,
+            mirroring: Mirroring::Horizontal,
+            crc_control: false,
+            previous_crc_control: false,
+            transfer_start: false,
+            disk_irq_enabled: false,
+            disk_irq: Cell::new(false),
+            transfer_complete: Cell::new(false),
+            read_data: 0,
+            write_data: 0,
+            end_of_head: true,
+            scanning: false,
+            gap_ended: false,
+            position: 0,
+            delay: 0,
+            crc: 0,
+            audio: FdsAudio::default(),
+        }
+    }
+
+    fn clock_timer(&mut self) {
+        if !self.timer_enabled || !self.disk_registers {
+            return;
+        }
+        if self.timer_counter == 0 {
+            self.timer_irq.set(true);
+            self.timer_counter = self.timer_reload;
+            self.timer_enabled = self.timer_repeat;
+        } else {
+            self.timer_counter -= 1;
+        }
+    }
+
+    fn clock_insert(&mut self) {
+        if self.next_side.is_none() {
+            return;
+        }
+        self.insert = self.insert.saturating_sub(1);
+        if self.insert == 0 {
+            self.side = self.next_side.take();
+        }
+    }
+
+    /// the drive moves the head a byte every BYTE_CYCLES while the motor runs
+    fn clock_drive(&mut self) {
+        let side = match self.side {
+            Some(side) if self.motor => side,
+            _ => {
+                self.end_of_head = true;
+                self.scanning = false;
+                return;
+            }
+        };
+        if self.transfer_reset && !self.scanning {
+            return;
+        }
+        if self.end_of_head {
+            self.delay = SPIN_UP_CYCLES;
+            self.end_of_head = false;
+            self.position = 0;
+            self.gap_ended = false;
+            return;
+        }
+        if self.delay > 0 {
+            self.delay -= 1;
+            return;
+        }
+
+        self.scanning = true;
+        let mut irq = self.disk_irq_enabled;
+        if self.read_mode {
+            let v = self.sides[side][self.position];
+            if !self.previous_crc_control {
+                self.crc = fds::update_crc(self.crc, v);
+            }
+            if !self.transfer_start {
+                self.gap_ended = false;
+                self.crc = 0;
+            } else if v != 0 && !self.gap_ended {
+                // the start mark 0x80 is not passed to the CPU
+                self.gap_ended = true;
+                irq = false;
+            }
+            if self.gap_ended {
+                self.transfer_complete.set(true);
+                self.read_data = v;
+                if irq {
+                    self.disk_irq.set(true);
+                }
+            }
+        } else {
+            let mut v = 0;
+            if !self.crc_control {
+                self.transfer_complete.set(true);
+                v = self.write_data;
+                if irq {
+                    self.disk_irq.set(true);
+                }
+            }
+            if !self.transfer_start {
+                v = 0;
+            }
+            if !self.crc_control {
+                self.crc = fds::update_crc(self.crc, v);
+            } else {
+                if !self.previous_crc_control {
+                    self.crc = fds::update_crc(self.crc, 0);
+                    self.crc = fds::update_crc(self.crc, 0);
+                }
+                v = self.crc as u8;
+                self.crc >>= 8;
+            }
+            self.sides[side][self.position] = v;
+            self.gap_ended = false;
+        }
+        self.previous_crc_control = self.crc_control;
+
+        self.position += 1;
+        if self.position >= self.sides[side].len() {
+            self.motor = false;
+        } else {
+            self.delay = BYTE_CYCLES;
+        }
+    }
+}
+
+impl RAM<usize> for FDS {}
+
+impl ROM<usize> for FDS {
+    type Output = u8;
+
+    fn get(&self, i: usize) -> Result<Self::Output> {
+        match i {
+            0x4030 if self.disk_registers => {
+                let v = self.timer_irq.get() as u8
+                    | (self.transfer_complete.get() as u8) << 1
+                    | (self.end_of_head as u8) << 6;
+                self.timer_irq.set(false);
+                self.disk_irq.set(false);
+                self.transfer_complete.set(false);
+                Ok(v)
+            }
+            0x4031 if self.disk_registers => {
+                self.disk_irq.set(false);
+                self.transfer_complete.set(false);
+                Ok(self.read_data)
+            }
+            0x4032 if self.disk_registers => {
+                let ejected = self.side.is_none();
+                Ok(ejected as u8
+                    | ((ejected || !self.scanning) as u8) << 1
+                    | (ejected as u8) << 2
+                    | 0x40)
+            }
+            // battery is good
+            0x4033 if self.disk_registers => Ok(0x80),
+            0x4040..=0x4097 if self.sound_registers => Ok(self.audio.get(i) | 0x40),
+            _ if (0x4020..=0x5FFF).contains(&i) => Ok(0),
+            0x6000..=0xDFFF => self.ram.get(i - 0x6000),
+            0xE000..=0xFFFF => self.bios.get(i - 0xE000),
+            _ => Err(e::index_out_of_range(i)),
+        }
+    }
+}
+
+impl WOM<usize> for FDS {
+    type Input = u8;
+
+    fn put(&mut self, i: usize, v: Self::Input) -> Result<()> {
+        match i {
+            0x4020 => self.timer_reload = (self.timer_reload & 0xFF00) | v as u16,
+            0x4021 => self.timer_reload = (self.timer_reload & 0x00FF) | (v as u16) << 8,
+            0x4022 => {
+                self.timer_repeat = v.bit(0);
+                self.timer_enabled = v.bit(1) && self.disk_registers;
+                if self.timer_enabled {
+                    self.timer_counter = self.timer_reload;
+                } else {
+                    self.timer_irq.set(false);
+                }
+            }
+            0x4023 => {
+                self.disk_registers = v.bit(0);
+                self.sound_registers = v.bit(1);
+                if !self.disk_registers {
+                    self.timer_enabled = false;
+                    self.timer_irq.set(false);
+                    self.disk_irq.set(false);
+                }
+            }
+            0x4024 if self.disk_registers => {
+                self.write_data = v;
+                self.transfer_complete.set(false);
+                self.disk_irq.set(false);
+            }
+            0x4025 if self.disk_registers => {
+                self.motor = v.bit(0);
+                self.transfer_reset = v.bit(1);
+                self.read_mode = v.bit(2);
+                self.mirroring = if v.bit(3) {
+                    Mirroring::Horizontal
+                } else {
+                    Mirroring::Vertical
+                };
+                self.crc_control = v.bit(4);
+                self.transfer_start = v.bit(6);
+                self.disk_irq_enabled = v.bit(7);
+                self.disk_irq.set(false);
+            }
+            0x4040..=0x4097 if self.sound_registers => self.audio.put(i, v),
+            _ if (0x4020..=0x5FFF).contains(&i) => {}
+            0x6000..=0xDFFF => self.ram.put(i - 0x6000, v)?,
+            _ => return Err(e::readonly(i)),
+        }
+        Ok(())
+    }
+}
+
+impl Mapper for FDS {
+    fn character(&self, i: usize) -> Result<u8> {
+        self.character.get(i)
+    }
+
+    fn put_character(&mut self, i: usize, v: u8) -> Result<()> {
+        self.character.put(i, v)
+    }
+
+    fn sprite(&self, i: usize) -> Sprite {
+        self.character.sprite(i)
+    }
+
+    fn mirroring(&self) -> Option<Mirroring> {
+        Some(self.mirroring)
+    }
+
+    fn clock(&mut self) {
+        self.clock_timer();
+        self.clock_insert();
+        self.clock_drive();
+        self.audio.clock();
+    }
+
+    fn irq(&self) -> bool {
+        self.timer_irq.get() || self.disk_irq.get()
+    }
+
+    fn sample(&self) -> f32 {
+        self.audio.output()
+    }
+
+    /// IPS patch of the written disk in the .fds layout, None until a block is written
+    fn battery(&self) -> Option<Vec<u8>> {
+        let original = layout(&self.original);
+        let sides = layout(&self.sides);
+        if sides == original {
+            return None;
+        }
+        // the patch addresses the sides in one buffer, as a headerless .fds
+        Some(fds::diff(&original, &sides))
+    }
+
+    fn load_battery(&mut self, data: &[u8]) {
+        let mut disk = layout(&self.original);
+        // a broken patch leaves the disk as loaded
+        if fds::apply(&mut disk, data).is_err() {
+            return;
+        }
+        let sides = disk
+            .chunks(fds::SIDE_LENGTH)
+            .map(|side| fds::stream(side, 0))
+            .collect::<Result<Vec<_>>>();
+        if let Ok(sides) = sides {
+            self.sides = sides;
+        }
+    }
+
+    fn disk_sides(&self) -> usize {
+        self.sides.len()
+    }
+
+    fn disk(&self) -> Option<usize> {
+        self.next_side.or(self.side)
+    }
+
+    fn insert_disk(&mut self, side: Option<usize>) {
+        self.side = None;
+        self.next_side = side.filter(|&n| n < self.sides.len());
+        self.insert = INSERT_CYCLES;
+    }
+}
+
+/// sides in the .fds layout, concatenated
+fn layout(sides: &[Vec<u8>]) -> Vec<u8> {
+    sides.iter().flat_map(|side| fds::side(side)).collect()
+}
+
+#[cfg(test)]
+fn mock() -> FDS {
+    let mut bios = vec![0; 0x2000];
+    bios[0x1FFC] = 0x42;
+    let disk = fds::DiskImage::parse(&crate::fds::mock_side()).unwrap();
+    let mut m = FDS::new(&bios, disk.sides().to_vec());
+    m.put(0x4023, 0b11).unwrap();
+    m
+}
+
+#[test]
+fn it_memory() {
+    let mut m = mock();
+    assert_eq!(m.get(0xFFFC).unwrap(), 0x42);
+    assert!(m.put(0xE000, 0).is_err());
+    m.put(0xDFFF, 0x12).unwrap();
+    assert_eq!(m.get(0xDFFF).unwrap(), 0x12);
+    m.put_character(0x1FFF, 0x34).unwrap();
+    assert_eq!(m.character(0x1FFF).unwrap(), 0x34);
+    m.put(0x4025, 0b0010_1110).unwrap();
+    assert_eq!(m.mirroring(), Some(Mirroring::Horizontal));
+}
+
+#[test]
+fn it_timer_irq() {
+    let mut m = mock();
+    m.put(0x4020, 2).unwrap();
+    m.put(0x4021, 0).unwrap();
+    m.put(0x4022, 0b11).unwrap();
+    for _ in 0..2 {
+        m.clock();
+    }
+    assert!(!m.irq());
+    m.clock();
+    assert!(m.irq());
+    assert_eq!(m.get(0x4030).unwrap() & 1, 1);
+    assert!(!m.irq());
+    // repeats
+    for _ in 0..3 {
+        m.clock();
+    }
+    assert!(m.irq());
+}
+
+#[test]
+fn it_read_disk() {
+    let mut m = mock();
+    // motor on, read mode, transfer start, disk IRQ
+    m.put(0x4025, 0b1100_0101).unwrap();
+    assert_eq!(m.get(0x4032).unwrap() & 0b11, 0b10);
+    let mut data = vec![];
+    for _ in 0..SPIN_UP_CYCLES + BYTE_CYCLES * 5000 {
+        m.clock();
+        if m.irq() {
+            data.push(m.get(0x4031).unwrap());
+            if data.len() == 15 {
+                break;
+            }
+        }
+    }
+    assert_eq!(&data[..], b"\x01*NINTENDO-HVC*");
+    assert_eq!(m.get(0x4032).unwrap() & 0b11, 0b00);
+}
+
+#[test]
+fn it_write_patch() {
+    let mut m = mock();
+    assert_eq!(m.battery(), None);
+    // motor on, write mode, the bytes go to the leading gap
+    m.put(0x4025, 0b0100_0001).unwrap();
+    m.put(0x4024, 0x5A).unwrap();
+    for _ in 0..SPIN_UP_CYCLES + BYTE_CYCLES * 3 {
+        m.clock();
+    }
+    assert!(m.sides != m.original);
+    assert_eq!(m.battery(), None);
+
+    // the file data 0xAA of the 4th block
+    let offset = 56 + 2 + 16 + 1;
+    let position = m.sides[0].iter().position(|&v| v == 0xAA).unwrap();
+    m.sides[0][position] = 0x11;
+    let patch = m.battery().unwrap();
+    assert!(patch.starts_with(b"PATCH"));
+    let mut side = crate::fds::mock_side();
+    fds::apply(&mut side, &patch).unwrap();
+    assert_eq!(side[offset], 0x11);
+
+    let mut other = mock();
+    other.load_battery(&patch);
+    assert_eq!(layout(&other.sides), layout(&m.sides));
+    assert!(other.original != other.sides);
+}
+
+#[test]
+fn it_switch_side() {
+    let mut m = mock();
+    assert_eq!(m.disk(), Some(0));
+    m.insert_disk(Some(0));
+    assert_eq!(m.get(0x4032).unwrap() & 1, 1);
+    for _ in 0..INSERT_CYCLES {
+        m.clock();
+    }
+    assert_eq!(m.get(0x4032).unwrap() & 1, 0);
+    m.insert_disk(None);
+    assert_eq!(m.disk(), None);
+}
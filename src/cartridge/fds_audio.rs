@@ -0,0 +1,305 @@
+use crate::bits::Byte;
+
+/// volume of the wave by 0x4089 bit 0～1, in 1/36
+const MASTER_VOLUMES: [u32; 4] = [36, 24, 17, 14];
+/// change of the modulation counter by the table value, None resets it
+const MODULATION_STEPS: [Option<i8>; 8] = [
+    Some(0),
+    Some(1),
+    Some(2),
+    Some(4),
+    None,
+    Some(-4),
+    Some(-2),
+    Some(-1),
+];
+
+/// volume or modulation gain envelope
+#[derive(Debug, Default)]
+struct Envelope {
+    speed: u8,
+    increase: bool,
+    /// the gain is the speed value directly
+    disabled: bool,
+    gain: u8,
+    timer: u32,
+}
+
+impl Envelope {
+    fn put_control(&mut self, v: u8, master: u8) {
+        self.speed = v & 0x3F;
+        self.increase = v.bit(6);
+        self.disabled = v.bit(7);
+        self.reset(master);
+        if self.disabled {
+            self.gain = self.speed;
+        }
+    }
+
+    fn reset(&mut self, master: u8) {
+        self.timer = 8 * (self.speed as u32 + 1) * master as u32;
+    }
+
+    /// returns true when the gain changes
+    fn clock(&mut self, master: u8) -> bool {
+        if self.disabled || master == 0 {
+            return false;
+        }
+        self.timer = self.timer.saturating_sub(1);
+        if self.timer > 0 {
+            return false;
+        }
+        self.reset(master);
+        if self.increase && self.gain < 32 {
+            self.gain += 1;
+        } else if !self.increase && self.gain > 0 {
+            self.gain -= 1;
+        }
+        true
+    }
+}
+
+/// FDS sound, a 64 step wavetable channel with a frequency modulator
+#[derive(Debug)]
+pub struct FdsAudio {
+    /// 6 bit samples
+    wave: [u8; 64],
+    /// 0x4089 bit 7, the wave is writable and halted
+    wave_write: bool,
+    master_volume: usize,
+    /// 0x4083 bit 7, the wave stays at the first sample
+    wave_halt: bool,
+    /// 0x4083 bit 6
+    envelope_halt: bool,
+    /// 0x408A, multiplies the envelope periods
+    envelope_speed: u8,
+    frequency: u16,
+    wave_position: usize,
+    /// 16 bit accumulator, the wave steps when it overflows
+    wave_accumulator: u16,
+    volume: Envelope,
+
+    modulation: Envelope,
+    /// 3 bit steps
+    modulation_table: [u8; 64],
+    modulation_position: usize,
+    /// 7 bit signed
+    modulation_counter: i8,
+    modulation_frequency: u16,
+    /// 0x4087 bit 7, the table is writable
+    modulation_halt: bool,
+    modulation_accumulator: u16,
+    /// pitch change by the modulator
+    modulation_output: i32,
+
+    output: u8,
+}
+
+impl Default for FdsAudio {
+    fn default() -> Self {
+        Self {
+            wave: [0; 64],
+            wave_write: false,
+            master_volume: 0,
+            wave_halt: false,
+            envelope_halt: false,
+            envelope_speed: 0xE8,
+            frequency: 0,
+            wave_position: 0,
+            wave_accumulator: 0,
+            volume: Envelope::default(),
+            modulation: Envelope::default(),
+            modulation_table: [0; 64],
+            modulation_position: 0,
+            modulation_counter: 0,
+            modulation_frequency: 0,
+            modulation_halt: true,
+            modulation_accumulator: 0,
+            modulation_output: 0,
+            output: 0,
+        }
+    }
+}
+
+impl FdsAudio {
+    /// 0x4040～0x4097
+    pub fn get(&self, i: usize) -> u8 {
+        match i {
+            0x4040..=0x407F => self.wave[i - 0x4040],
+            0x4090 => self.volume.gain,
+            0x4092 => self.modulation.gain,
+            _ => 0,
+        }
+    }
+
+    /// 0x4040～0x408A
+    pub fn put(&mut self, i: usize, v: u8) {
+        match i {
+            0x4040..=0x407F if self.wave_write => self.wave[i - 0x4040] = v & 0x3F,
+            0x4080 => self.volume.put_control(v, self.envelope_speed),
+            0x4082 => self.frequency = (self.frequency & 0x0F00) | v as u16,
+            0x4083 => {
+                self.frequency = (self.frequency & 0x00FF) | ((v & 0x0F) as u16) << 8;
+                self.wave_halt = v.bit(7);
+                self.envelope_halt = v.bit(6);
+                if self.wave_halt {
+                    self.wave_position = 0;
+                    self.wave_accumulator = 0;
+                }
+                if self.envelope_halt {
+                    self.volume.reset(self.envelope_speed);
+                    self.modulation.reset(self.envelope_speed);
+                }
+            }
+            0x4084 => self.modulation.put_control(v, self.envelope_speed),
+            0x4085 => self.set_counter(v as i32 & 0x7F),
+            0x4086 => {
+                self.modulation_frequency = (self.modulation_frequency & 0x0F00) | v as u16;
+            }
+            0x4087 => {
+                self.modulation_frequency =
+                    (self.modulation_frequency & 0x00FF) | ((v & 0x0F) as u16) << 8;
+                self.modulation_halt = v.bit(7);
+                if self.modulation_halt {
+                    self.modulation_accumulator = 0;
+                }
+            }
+            // one write fills two steps of the table
+            0x4088 if self.modulation_halt => {
+                for _ in 0..2 {
+                    self.modulation_table[self.modulation_position] = v & 0b111;
+                    self.modulation_position = (self.modulation_position + 1) & 0x3F;
+                }
+            }
+            0x4089 => {
+                self.master_volume = (v & 0b11) as usize;
+                self.wave_write = v.bit(7);
+            }
+            0x408A => self.envelope_speed = v,
+            _ => {}
+        }
+    }
+
+    /// wraps to -64～63
+    fn set_counter(&mut self, v: i32) {
+        self.modulation_counter = ((v + 64).rem_euclid(128) - 64) as i8;
+    }
+
+    /// pitch change of the current counter and gain
+    fn update_modulation(&mut self) {
+        let counter = self.modulation_counter as i32;
+        let mut temp = counter * self.modulation.gain as i32;
+        let remainder = temp & 0x0F;
+        temp >>= 4;
+        if remainder > 0 && temp & 0x80 == 0 {
+            temp += if counter < 0 { -1 } else { 2 };
+        }
+        if temp >= 192 {
+            temp -= 256;
+        } else if temp < -64 {
+            temp += 256;
+        }
+        temp *= self.frequency as i32;
+        let remainder = temp & 0x3F;
+        temp >>= 6;
+        if remainder >= 32 {
+            temp += 1;
+        }
+        self.modulation_output = temp;
+    }
+
+    fn clock_modulator(&mut self) -> bool {
+        if self.modulation_halt || self.modulation_frequency == 0 {
+            return false;
+        }
+        let (accumulator, overflow) = self
+            .modulation_accumulator
+            .overflowing_add(self.modulation_frequency);
+        self.modulation_accumulator = accumulator;
+        if !overflow {
+            return false;
+        }
+        match MODULATION_STEPS[self.modulation_table[self.modulation_position] as usize] {
+            Some(step) => self.set_counter(self.modulation_counter as i32 + step as i32),
+            None => self.set_counter(0),
+        }
+        self.modulation_position = (self.modulation_position + 1) & 0x3F;
+        true
+    }
+
+    pub fn clock(&mut self) {
+        if !self.wave_halt && !self.envelope_halt {
+            self.volume.clock(self.envelope_speed);
+            if self.modulation.clock(self.envelope_speed) {
+                self.update_modulation();
+            }
+        }
+        if self.clock_modulator() {
+            self.update_modulation();
+        }
+
+        let gain = self.volume.gain.min(32) as u32;
+        let level = gain * MASTER_VOLUMES[self.master_volume];
+        // the output holds while the wave is written
+        if !self.wave_write {
+            self.output = (self.wave[self.wave_position] as u32 * level / 1152) as u8;
+        }
+        let frequency = self.frequency as i32 + self.modulation_output;
+        if !self.wave_halt && !self.wave_write && frequency > 0 {
+            let (accumulator, overflow) = self.wave_accumulator.overflowing_add(frequency as u16);
+            self.wave_accumulator = accumulator;
+            if overflow {
+                self.wave_position = (self.wave_position + 1) & 0x3F;
+            }
+        }
+    }
+
+    /// 0.0～1.0
+    pub fn output(&self) -> f32 {
+        self.output as f32 / 63.0
+    }
+}
+
+#[test]
+fn it_wave() {
+    let mut audio = FdsAudio::default();
+    audio.put(0x4089, 0x80);
+    for i in 0..64 {
+        audio.put(0x4040 + i, if i < 32 { 63 } else { 0 });
+    }
+    audio.put(0x4089, 0x00);
+    // volume envelope off, gain 32
+    audio.put(0x4080, 0x80 | 32);
+    assert_eq!(audio.get(0x4090), 32);
+    // one step every 32 cycles
+    audio.put(0x4082, 0x00);
+    audio.put(0x4083, 0x08);
+    audio.clock();
+    assert_eq!(audio.output, 63);
+    for _ in 0..32 * 32 {
+        audio.clock();
+    }
+    assert_eq!(audio.wave_position, 32);
+    audio.clock();
+    assert_eq!(audio.output, 0);
+}
+
+#[test]
+fn it_modulation() {
+    let mut audio = FdsAudio::default();
+    audio.put(0x4087, 0x80);
+    // +1 for every step
+    for _ in 0..32 {
+        audio.put(0x4088, 1);
+    }
+    audio.put(0x4084, 0x80 | 0x20);
+    audio.put(0x4085, 0x3F);
+    audio.put(0x4086, 0xFF);
+    audio.put(0x4087, 0x0F);
+    // the accumulator overflows at the 17th cycle
+    for _ in 0..17 {
+        audio.clock();
+    }
+    // 63 + 1 wraps to -64
+    assert_eq!(audio.modulation_counter, -64);
+}
@@ -0,0 +1,259 @@
+use super::bank;
+use super::battery;
+use super::character::Character;
+use super::mapper::{Mapper, Mirroring};
+use super::sunsoft5b_audio::Sunsoft5bAudio;
+use crate::bits::Byte;
+use crate::memory::{RAM, ROM, WOM};
+use crate::result::{e, Result};
+use crate::sprite::Sprite;
+
+/// mapper 69 (Sunsoft FME-7 / 5A / 5B)
+/// registers are written through a command register at 0x8000 and a parameter register at 0xA000.
+pub struct FME7 {
+    program: Vec<u8>,
+    character: Character,
+    ram: Vec<u8>,
+    battery: bool,
+    command: u8,
+    /// 1KB banks
+    character_banks: [usize; 8],
+    /// 8KB banks at 0x6000, 0x8000, 0xA000, 0xC000
+    program_banks: [usize; 4],
+    /// 0x6000 is mapped to RAM instead of ROM
+    ram_selected: bool,
+    ram_enabled: bool,
+    mirroring: Mirroring,
+    irq_enabled: bool,
+    counter_enabled: bool,
+    counter: u16,
+    irq: bool,
+    audio: Sunsoft5bAudio,
+}
+
+impl FME7 {
+    pub fn new(program: &[u8], character: Character, battery: bool) -> Self {
+        Self {
+            program: program.to_vec(),
+            character,
+            ram: vec![0; 0x2000],
+            battery,
+            command: 0,
+            character_banks: [0; 8],
+            program_banks: [0; 4],
+            ram_selected: false,
+            ram_enabled: false,
+            mirroring: Mirroring::Vertical,
+            irq_enabled: false,
+            counter_enabled: false,
+            counter: 0,
+            irq: false,
+            audio: Sunsoft5bAudio::default(),
+        }
+    }
+
+    fn program_offset(&self, i: usize) -> usize {
+        let len = self.program.len();
+        let n = match i {
+            0xE000..=0xFFFF => bank::last(len, 0x2000, 0),
+            _ => bank::offset(len, 0x2000, self.program_banks[(i - 0x6000) / 0x2000]),
+        };
+        n + (i & 0x1FFF)
+    }
+
+    fn character_offset(&self, i: usize) -> usize {
+        let n = self.character_banks[i / 0x0400];
+        bank::offset(self.character.len(), 0x0400, n) + (i & 0x03FF)
+    }
+
+    fn put_parameter(&mut self, v: u8) {
+        match self.command {
+            c @ 0x0..=0x7 => self.character_banks[c as usize] = v as usize,
+            0x8 => {
+                self.program_banks[0] = (v & 0x3F) as usize;
+                self.ram_selected = v.bit(6);
+                self.ram_enabled = v.bit(7);
+            }
+            c @ 0x9..=0xB => self.program_banks[c as usize - 0x8] = (v & 0x3F) as usize,
+            0xC => {
+                self.mirroring = match v & 0b11 {
+                    0 => Mirroring::Vertical,
+                    1 => Mirroring::Horizontal,
+                    2 => Mirroring::SingleScreenA,
+                    _ => Mirroring::SingleScreenB,
+                }
+            }
+            0xD => {
+                self.irq_enabled = v.bit(0);
+                self.counter_enabled = v.bit(7);
+                self.irq = false;
+            }
+            0xE => self.counter = (self.counter & 0xFF00) | v as u16,
+            _ => self.counter = (self.counter & 0x00FF) | (v as u16) << 8,
+        }
+    }
+}
+
+impl RAM<usize> for FME7 {}
+
+impl ROM<usize> for FME7 {
+    type Output = u8;
+
+    fn get(&self, i: usize) -> Result<Self::Output> {
+        match i {
+            _ if (0x4020..=0x5FFF).contains(&i) => Ok(0),
+            _ if (0x6000..=0x7FFF).contains(&i) => match (self.ram_selected, self.ram_enabled) {
+                (true, true) => self.ram.get(i - 0x6000),
+                // open bus
+                (true, false) => Ok(0),
+                (false, _) => self.program.get(self.program_offset(i)),
+            },
+            _ if (0x8000..=0xFFFF).contains(&i) => self.program.get(self.program_offset(i)),
+            _ => Err(e::index_out_of_range(i)),
+        }
+    }
+}
+
+impl WOM<usize> for FME7 {
+    type Input = u8;
+
+    fn put(&mut self, i: usize, v: Self::Input) -> Result<()> {
+        match i {
+            _ if (0x6000..=0x7FFF).contains(&i) => {
+                if self.ram_selected && self.ram_enabled {
+                    self.ram.put(i - 0x6000, v)?;
+                }
+                Ok(())
+            }
+            _ if (0x8000..=0x9FFF).contains(&i) => {
+                self.command = v & 0x0F;
+                Ok(())
+            }
+            _ if (0xA000..=0xBFFF).contains(&i) => {
+                self.put_parameter(v);
+                Ok(())
+            }
+            _ if (0xC000..=0xDFFF).contains(&i) => {
+                self.audio.put_address(v);
+                Ok(())
+            }
+            _ if (0xE000..=0xFFFF).contains(&i) => {
+                self.audio.put_data(v);
+                Ok(())
+            }
+            _ => Err(e::readonly(i)),
+        }
+    }
+}
+
+impl Mapper for FME7 {
+    fn character(&self, i: usize) -> Result<u8> {
+        self.character.get(self.character_offset(i))
+    }
+
+    fn put_character(&mut self, i: usize, v: u8) -> Result<()> {
+        self.character.put(self.character_offset(i), v)
+    }
+
+    fn sprite(&self, i: usize) -> Sprite {
+        self.character.sprite(self.character_offset(i))
+    }
+
+    fn mirroring(&self) -> Option<Mirroring> {
+        Some(self.mirroring)
+    }
+
+    fn clock(&mut self) {
+        if self.counter_enabled {
+            self.counter = self.counter.wrapping_sub(1);
+            if self.counter == 0xFFFF && self.irq_enabled {
+                self.irq = true;
+            }
+        }
+        self.audio.clock();
+    }
+
+    fn irq(&self) -> bool {
+        self.irq
+    }
+
+    fn sample(&self) -> f32 {
+        self.audio.output()
+    }
+
+    fn battery(&self) -> Option<Vec<u8>> {
+        self.battery.then(|| self.ram.clone())
+    }
+
+    fn load_battery(&mut self, data: &[u8]) {
+        battery::restore(&mut self.ram, data);
+    }
+}
+
+#[cfg(test)]
+fn mock() -> FME7 {
+    let program = (0..0x2000 * 32).map(|i| (i / 0x2000) as u8).collect::<Vec<u8>>();
+    let character = (0..0x0400 * 256).map(|i| (i / 0x0400) as u8).collect::<Vec<u8>>();
+    FME7::new(&program, Character::new(&character), false)
+}
+
+#[cfg(test)]
+fn command(m: &mut FME7, c: u8, v: u8) {
+    m.put(0x8000, c).unwrap();
+    m.put(0xA000, v).unwrap();
+}
+
+#[test]
+fn it_program_banks() {
+    let mut m = mock();
+    command(&mut m, 0x9, 3);
+    command(&mut m, 0xA, 4);
+    command(&mut m, 0xB, 5);
+    assert_eq!(m.get(0x8000).unwrap(), 3);
+    assert_eq!(m.get(0xA000).unwrap(), 4);
+    assert_eq!(m.get(0xC000).unwrap(), 5);
+    assert_eq!(m.get(0xE000).unwrap(), 31);
+}
+
+#[test]
+fn it_ram() {
+    let mut m = mock();
+    // ROM bank 7 at 0x6000
+    command(&mut m, 0x8, 7);
+    assert_eq!(m.get(0x6000).unwrap(), 7);
+    m.put(0x6000, 0x55).unwrap();
+    assert_eq!(m.get(0x6000).unwrap(), 7);
+
+    // RAM selected and enabled
+    command(&mut m, 0x8, 0b1100_0000);
+    m.put(0x6000, 0x55).unwrap();
+    assert_eq!(m.get(0x6000).unwrap(), 0x55);
+
+    // RAM selected but disabled
+    command(&mut m, 0x8, 0b0100_0000);
+    assert_eq!(m.get(0x6000).unwrap(), 0);
+}
+
+#[test]
+fn it_character_banks_and_mirroring() {
+    let mut m = mock();
+    command(&mut m, 0x5, 0x81);
+    assert_eq!(m.character(0x1400).unwrap(), 0x81);
+    command(&mut m, 0xC, 3);
+    assert_eq!(m.mirroring(), Some(Mirroring::SingleScreenB));
+}
+
+#[test]
+fn it_irq() {
+    let mut m = mock();
+    command(&mut m, 0xE, 0x01);
+    command(&mut m, 0xF, 0x00);
+    command(&mut m, 0xD, 0b1000_0001);
+    m.clock();
+    assert!(!m.irq());
+    m.clock();
+    assert!(m.irq());
+    // writing the control register acknowledges
+    command(&mut m, 0xD, 0b1000_0000);
+    assert!(!m.irq());
+}
@@ -0,0 +1,128 @@
+use crate::memory::RAM;
+use crate::result::Result;
+use crate::sprite::Sprite;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mirroring {
+    Horizontal,
+    Vertical,
+    /// all nametables show the first one
+    SingleScreenA,
+    /// all nametables show the second one
+    SingleScreenB,
+    /// the board has extra VRAM for all four nametables
+    FourScreen,
+}
+
+impl Mirroring {
+    /// VRAM page of the nametable slot
+    /// | 0 | 1 |
+    /// | 2 | 3 |
+    /// four-screen boards add the pages 2 and 3.
+    pub fn page(&self, slot: usize) -> usize {
+        match self {
+            Mirroring::Horizontal => slot / 2,
+            Mirroring::Vertical => slot % 2,
+            Mirroring::SingleScreenA => 0,
+            Mirroring::SingleScreenB => 1,
+            Mirroring::FourScreen => slot,
+        }
+    }
+}
+
+/// Cartridge board.
+/// CPU side is addressed by the absolute CPU address (0x4020～0xFFFF),
+/// PPU side is addressed by the PPU address of the pattern tables (0x0000～0x1FFF).
+pub trait Mapper: RAM<usize, Output = u8, Input = u8> {
+    /// read a byte of the pattern tables
+    fn character(&self, i: usize) -> Result<u8>;
+
+    /// write a byte of the pattern tables
+    fn put_character(&mut self, i: usize, v: u8) -> Result<()>;
+
+    /// decoded tile which starts at the PPU address
+    fn sprite(&self, i: usize) -> Sprite;
+
+    /// decoded tile for objects (OAM sprites).
+    /// some boards have separate banks for objects.
+    fn object_sprite(&self, i: usize) -> Sprite {
+        self.sprite(i)
+    }
+
+    /// nametable byte (0x2000～0x2FFF) when the board maps its own memory there
+    fn nametable(&self, _i: usize) -> Option<u8> {
+        None
+    }
+
+    /// returns true when the board takes the nametable write
+    fn put_nametable(&mut self, _i: usize, _v: u8) -> bool {
+        false
+    }
+
+    /// how the nametables are arranged.
+    /// None when the board selects a page for each nametable by `vram_page`.
+    fn mirroring(&self) -> Option<Mirroring>;
+
+    /// VRAM page read as the pattern table at the PPU address, some boards map it as CHR
+    fn character_vram_page(&self, _i: usize) -> Option<usize> {
+        None
+    }
+
+    /// VRAM page of the nametable slot (0: 0x2000, 1: 0x2400, 2: 0x2800, 3: 0x2C00)
+    fn vram_page(&self, slot: usize) -> usize {
+        self.mirroring()
+            .map_or(slot % 2, |mirroring| mirroring.page(slot))
+    }
+
+    /// PPU reads pattern tables and nametables while rendering.
+    /// some boards switch banks or count scanlines by watching this address.
+    fn fetch(&mut self, _i: usize) {}
+
+    /// PPU stopped rendering (vblank)
+    fn idle(&mut self) {}
+
+    /// CPU writes to the PPU registers (0x2000～0x2007), also seen by the cartridge
+    fn ppu_register(&mut self, _i: usize, _v: u8) {}
+
+    /// called once per CPU cycle
+    fn clock(&mut self) {}
+
+    /// IRQ line of the cartridge, it stays asserted until the CPU acknowledges it
+    fn irq(&self) -> bool {
+        false
+    }
+
+    /// expansion audio output in 0.0～1.0, mixed with the APU
+    fn sample(&self) -> f32 {
+        0.0
+    }
+
+    /// trainer of the ROM file, placed at 0x7000～0x71FF.
+    /// boards without RAM there just ignore it.
+    fn load_trainer(&mut self, data: &[u8]) {
+        for (i, v) in data.iter().enumerate() {
+            let _ = self.put(0x7000 + i, *v);
+        }
+    }
+
+    /// battery-backed memory of the board, None when the board has no battery
+    fn battery(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// restore the battery-backed memory saved by `battery`
+    fn load_battery(&mut self, _data: &[u8]) {}
+
+    /// number of disk sides, 0 for ROM cartridges
+    fn disk_sides(&self) -> usize {
+        0
+    }
+
+    /// disk side in the drive, or being inserted
+    fn disk(&self) -> Option<usize> {
+        None
+    }
+
+    /// eject the disk, and insert the side after a while when it is Some
+    fn insert_disk(&mut self, _side: Option<usize>) {}
+}
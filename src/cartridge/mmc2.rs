@@ -0,0 +1,225 @@
+use super::bank;
+use super::battery;
+use super::character::Character;
+use super::mapper::{Mapper, Mirroring};
+use crate::memory::{RAM, ROM, WOM};
+use crate::result::{e, Result};
+use crate::sprite::Sprite;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Kind {
+    /// mapper 9, 8KB switchable PRG bank (Punch-Out!!)
+    MMC2,
+    /// mapper 10, 16KB switchable PRG bank and PRG RAM (Fire Emblem)
+    MMC4,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Latch {
+    FD,
+    FE,
+}
+
+/// mapper 9 / 10
+/// each 4KB pattern table has two CHR banks and a latch.
+/// the latch is switched when the PPU fetches tile $FD or $FE,
+/// so the bank changes right after that tile is drawn.
+pub struct MMC2 {
+    kind: Kind,
+    program: Vec<u8>,
+    character: Character,
+    ram: Vec<u8>,
+    battery: bool,
+    program_bank: usize,
+    /// [0x0000 bank for $FD, for $FE], [0x1000 bank for $FD, for $FE]
+    character_banks: [[usize; 2]; 2],
+    latches: [Latch; 2],
+    mirroring: Mirroring,
+}
+
+impl MMC2 {
+    pub fn new(kind: Kind, program: &[u8], character: Character, battery: bool) -> Self {
+        Self {
+            kind,
+            program: program.to_vec(),
+            character,
+            ram: vec![0; 0x2000],
+            battery,
+            program_bank: 0,
+            character_banks: [[0; 2]; 2],
+            latches: [Latch::FE; 2],
+            mirroring: Mirroring::Vertical,
+        }
+    }
+
+    fn program_offset(&self, i: usize) -> usize {
+        let len = self.program.len();
+        match self.kind {
+            Kind::MMC2 => match i {
+                _ if (0x8000..=0x9FFF).contains(&i) => {
+                    bank::offset(len, 0x2000, self.program_bank) + (i - 0x8000)
+                }
+                // last three 8KB banks are fixed
+                _ => bank::last(len, 0x2000, 2) + (i - 0xA000),
+            },
+            Kind::MMC4 => match i {
+                _ if (0x8000..=0xBFFF).contains(&i) => {
+                    bank::offset(len, 0x4000, self.program_bank) + (i - 0x8000)
+                }
+                _ => bank::last(len, 0x4000, 0) + (i - 0xC000),
+            },
+        }
+    }
+
+    fn character_offset(&self, i: usize) -> usize {
+        let table = (i >> 12) & 1;
+        let latch = match self.latches[table] {
+            Latch::FD => 0,
+            Latch::FE => 1,
+        };
+        let n = self.character_banks[table][latch];
+        bank::offset(self.character.len(), 0x1000, n) + (i & 0x0FFF)
+    }
+}
+
+impl RAM<usize> for MMC2 {}
+
+impl ROM<usize> for MMC2 {
+    type Output = u8;
+
+    fn get(&self, i: usize) -> Result<Self::Output> {
+        match i {
+            _ if (0x4020..=0x5FFF).contains(&i) => Ok(0),
+            _ if (0x6000..=0x7FFF).contains(&i) => match self.kind {
+                Kind::MMC2 => Ok(0),
+                Kind::MMC4 => self.ram.get(i - 0x6000),
+            },
+            _ if (0x8000..=0xFFFF).contains(&i) => self.program.get(self.program_offset(i)),
+            _ => Err(e::index_out_of_range(i)),
+        }
+    }
+}
+
+impl WOM<usize> for MMC2 {
+    type Input = u8;
+
+    fn put(&mut self, i: usize, v: Self::Input) -> Result<()> {
+        match i {
+            _ if (0x6000..=0x7FFF).contains(&i) => match self.kind {
+                Kind::MMC2 => Err(e::readonly(i)),
+                Kind::MMC4 => self.ram.put(i - 0x6000, v),
+            },
+            _ if (0xA000..=0xAFFF).contains(&i) => {
+                self.program_bank = (v & 0x0F) as usize;
+                Ok(())
+            }
+            _ if (0xB000..=0xBFFF).contains(&i) => {
+                self.character_banks[0][0] = (v & 0x1F) as usize;
+                Ok(())
+            }
+            _ if (0xC000..=0xCFFF).contains(&i) => {
+                self.character_banks[0][1] = (v & 0x1F) as usize;
+                Ok(())
+            }
+            _ if (0xD000..=0xDFFF).contains(&i) => {
+                self.character_banks[1][0] = (v & 0x1F) as usize;
+                Ok(())
+            }
+            _ if (0xE000..=0xEFFF).contains(&i) => {
+                self.character_banks[1][1] = (v & 0x1F) as usize;
+                Ok(())
+            }
+            _ if (0xF000..=0xFFFF).contains(&i) => {
+                self.mirroring = if v & 1 == 0 {
+                    Mirroring::Vertical
+                } else {
+                    Mirroring::Horizontal
+                };
+                Ok(())
+            }
+            _ => Err(e::readonly(i)),
+        }
+    }
+}
+
+impl Mapper for MMC2 {
+    fn character(&self, i: usize) -> Result<u8> {
+        self.character.get(self.character_offset(i))
+    }
+
+    fn put_character(&mut self, i: usize, v: u8) -> Result<()> {
+        self.character.put(self.character_offset(i), v)
+    }
+
+    fn sprite(&self, i: usize) -> Sprite {
+        self.character.sprite(self.character_offset(i))
+    }
+
+    fn mirroring(&self) -> Option<Mirroring> {
+        Some(self.mirroring)
+    }
+
+    fn fetch(&mut self, i: usize) {
+        if i >= 0x2000 {
+            return;
+        }
+        let table = (i >> 12) & 1;
+        // MMC2 watches only the first row of the high plane on the left table
+        let exact = self.kind == Kind::MMC2 && table == 0;
+        let latch = match i & 0x0FFF {
+            0x0FD8 => Some(Latch::FD),
+            0x0FE8 => Some(Latch::FE),
+            0x0FD9..=0x0FDF if !exact => Some(Latch::FD),
+            0x0FE9..=0x0FEF if !exact => Some(Latch::FE),
+            _ => None,
+        };
+        if let Some(latch) = latch {
+            self.latches[table] = latch;
+        }
+    }
+
+    fn battery(&self) -> Option<Vec<u8>> {
+        // only MMC4 has PRG RAM
+        (self.battery && self.kind == Kind::MMC4).then(|| self.ram.clone())
+    }
+
+    fn load_battery(&mut self, data: &[u8]) {
+        battery::restore(&mut self.ram, data);
+    }
+}
+
+#[cfg(test)]
+fn mock(kind: Kind) -> MMC2 {
+    let program = vec![0; 0x2000 * 8];
+    let character = (0..0x1000 * 4)
+        .map(|i| (i / 0x1000) as u8)
+        .collect::<Vec<u8>>();
+    MMC2::new(kind, &program, Character::new(&character), false)
+}
+
+#[test]
+fn it_switch_by_latch() {
+    let mut m = mock(Kind::MMC2);
+    m.put(0xB000, 1).unwrap();
+    m.put(0xC000, 2).unwrap();
+    assert_eq!(m.character(0x0000).unwrap(), 2);
+    m.fetch(0x0FD8);
+    assert_eq!(m.character(0x0000).unwrap(), 1);
+    // only 0x0FD8 triggers the left latch on MMC2
+    m.fetch(0x0FE9);
+    assert_eq!(m.character(0x0000).unwrap(), 1);
+    m.fetch(0x0FE8);
+    assert_eq!(m.character(0x0000).unwrap(), 2);
+}
+
+#[test]
+fn it_switch_by_latch_range() {
+    let mut m = mock(Kind::MMC4);
+    m.put(0xD000, 3).unwrap();
+    m.put(0xE000, 0).unwrap();
+    assert_eq!(m.character(0x1000).unwrap(), 0);
+    m.fetch(0x1FDF);
+    assert_eq!(m.character(0x1000).unwrap(), 3);
+    m.fetch(0x0FE9);
+    assert_eq!(m.character(0x1000).unwrap(), 3);
+}
@@ -0,0 +1,651 @@
+use super::bank;
+use super::battery;
+use super::character::Character;
+use super::mapper::{Mapper, Mirroring};
+use super::mmc5_audio::Mmc5Audio;
+use crate::bits::Byte;
+use crate::memory::{RAM, ROM, WOM};
+use crate::result::{e, Result};
+use crate::sprite::Sprite;
+use std::cell::{Cell, RefCell};
+
+const EXRAM_LENGTH: usize = 0x0400;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum ExRamMode {
+    /// used as a nametable
+    Nametable,
+    /// each byte has the CHR bank and the palette of a background tile
+    ExtendedAttribute,
+    ReadWrite,
+    ReadOnly,
+}
+
+/// the last tile fetched by the PPU
+#[derive(Clone, Copy, Debug, Default)]
+struct Tile {
+    /// column in the scanline
+    x: usize,
+    /// ExRAM byte of the tile in extended attribute mode
+    extended: u8,
+    /// coarse row of the split region, when the tile is inside it
+    split: Option<usize>,
+    /// fine Y of the split region, it replaces the one of the PPU
+    split_fine_y: usize,
+}
+
+/// mapper 5 (Nintendo MMC5)
+pub struct MMC5 {
+    program: Vec<u8>,
+    character: Character,
+    /// up to 64KB PRG RAM in 8KB banks
+    ram: Vec<u8>,
+    battery: bool,
+    exram: Vec<u8>,
+
+    /// 0x5100
+    program_mode: u8,
+    /// 0x5101
+    character_mode: u8,
+    /// 0x5102, 0x5103
+    ram_protect: [u8; 2],
+    /// 0x5104
+    exram_mode: ExRamMode,
+    /// 0x5105, 2 bits for each nametable
+    nametable_mapping: u8,
+    /// 0x5106, 0x5107
+    fill_tile: u8,
+    fill_attribute: u8,
+    /// 0x5113～0x5117, bit 7 of 0x5114～0x5116 selects ROM
+    program_banks: [u8; 5],
+    /// 0x5120～0x5127, used by objects in 8x16 mode
+    object_banks: [usize; 8],
+    /// 0x5128～0x512B, used by backgrounds in 8x16 mode
+    background_banks: [usize; 4],
+    /// the latest written set, used in 8x8 mode
+    background_written: bool,
+    /// 0x5130
+    character_upper: usize,
+    /// 0x5200～0x5202
+    split_control: u8,
+    split_scroll: u8,
+    split_bank: usize,
+    /// 0x5203, 0x5204
+    irq_target: u8,
+    irq_enabled: bool,
+    irq_pending: Cell<bool>,
+    /// 0x5205, 0x5206
+    multiplicand: u8,
+    multiplier: u8,
+
+    /// PPUCTRL bit 5
+    large_sprites: bool,
+    in_frame: bool,
+    scanline: usize,
+    last_fetch: usize,
+    same_fetches: usize,
+    tile_count: usize,
+    tile: Tile,
+
+    audio: RefCell<Mmc5Audio>,
+}
+
+impl MMC5 {
+    pub fn new(program: &[u8], character: Character, battery: bool) -> Self {
+        Self {
+            program: program.to_vec(),
+            character,
+            ram: vec![0; 0x10000],
+            battery,
+            exram: vec![0; EXRAM_LENGTH],
+            program_mode: 3,
+            character_mode: 0,
+            ram_protect: [0; 2],
+            exram_mode: ExRamMode::Nametable,
+            nametable_mapping: 0,
+            fill_tile: 0,
+            fill_attribute: 0,
+            program_banks: [0, 0, 0, 0, 0xFF],
+            object_banks: [0; 8],
+            background_banks: [0; 4],
+            background_written: false,
+            character_upper: 0,
+            split_control: 0,
+            split_scroll: 0,
+            split_bank: 0,
+            irq_target: 0,
+            irq_enabled: false,
+            irq_pending: Cell::new(false),
+            multiplicand: 0xFF,
+            multiplier: 0xFF,
+            large_sprites: false,
+            in_frame: false,
+            scanline: 0,
+            last_fetch: 0,
+            same_fetches: 0,
+            tile_count: 0,
+            tile: Tile::default(),
+            audio: RefCell::new(Mmc5Audio::default()),
+        }
+    }
+
+    /// ROM or RAM offset of 0x6000～0xFFFF, true when it is ROM
+    fn program_offset(&self, i: usize) -> (bool, usize) {
+        if i < 0x8000 {
+            let n = (self.program_banks[0] & 0x0F) as usize;
+            return (
+                false,
+                bank::offset(self.ram.len(), 0x2000, n) + (i & 0x1FFF),
+            );
+        }
+
+        // (register, bank size) for each mode
+        let (register, size) = match (self.program_mode & 0b11, i) {
+            (0, _) => (4, 0x8000),
+            (1, 0x8000..=0xBFFF) => (2, 0x4000),
+            (1, _) => (4, 0x4000),
+            (2, 0x8000..=0xBFFF) => (2, 0x4000),
+            (2, 0xC000..=0xDFFF) => (3, 0x2000),
+            (2, _) => (4, 0x2000),
+            (_, _) => ((i - 0x8000) / 0x2000 + 1, 0x2000),
+        };
+        let v = self.program_banks[register];
+        let rom = register == 4 || v.bit(7);
+        // bank numbers are always in 8KB unit
+        let n = (v & 0x7F) as usize / (size / 0x2000);
+        let len = if rom {
+            self.program.len()
+        } else {
+            self.ram.len()
+        };
+        (rom, bank::offset(len, size, n) + (i & (size - 1)))
+    }
+
+    fn ram_writable(&self) -> bool {
+        self.ram_protect[0] & 0b11 == 0b10 && self.ram_protect[1] & 0b11 == 0b01
+    }
+
+    /// offset of 1KB-unit bank registers for the CHR mode
+    fn bank_offset(&self, banks: &[usize], i: usize) -> usize {
+        let size = 0x2000 >> self.character_mode;
+        // the last register of each window is used
+        let index = ((i / size + 1) * (size / 0x0400) - 1) % banks.len();
+        bank::offset(self.character.len(), size, banks[index]) + (i & (size - 1))
+    }
+
+    /// CPU accesses through 0x2007 use the latest written set
+    fn character_offset(&self, i: usize) -> usize {
+        if self.background_written {
+            self.bank_offset(&self.background_banks, i)
+        } else {
+            self.bank_offset(&self.object_banks, i)
+        }
+    }
+
+    fn object_offset(&self, i: usize) -> usize {
+        if !self.large_sprites && self.background_written {
+            self.bank_offset(&self.background_banks, i)
+        } else {
+            self.bank_offset(&self.object_banks, i)
+        }
+    }
+
+    fn background_offset(&self, i: usize) -> usize {
+        if self.tile.split.is_some() {
+            return bank::offset(self.character.len(), 0x1000, self.split_bank) + (i & 0x0FFF);
+        }
+        if self.exram_mode == ExRamMode::ExtendedAttribute {
+            let n = (self.tile.extended & 0x3F) as usize | self.character_upper << 6;
+            return bank::offset(self.character.len(), 0x1000, n) + (i & 0x0FFF);
+        }
+        if self.large_sprites || self.background_written {
+            self.bank_offset(&self.background_banks, i)
+        } else {
+            self.bank_offset(&self.object_banks, i)
+        }
+    }
+
+    /// the same address is read three times at the end of each scanline
+    fn detect_scanline(&mut self, i: usize) {
+        if i == self.last_fetch {
+            self.same_fetches += 1;
+            if self.same_fetches == 2 {
+                // the first two tiles were fetched at the end of the previous scanline
+                self.tile_count = 2;
+                if self.in_frame {
+                    self.scanline += 1;
+                    if self.scanline == self.irq_target as usize {
+                        self.irq_pending.set(true);
+                    }
+                } else {
+                    self.in_frame = true;
+                    self.scanline = 0;
+                    self.irq_pending.set(false);
+                }
+            }
+        } else {
+            self.same_fetches = 0;
+        }
+    }
+
+    fn fetch_name(&mut self, i: usize) {
+        let x = self.tile_count;
+        self.tile_count += 1;
+        // tiles after 32 are the first ones of the next scanline
+        let (x, scanline) = if x >= 32 {
+            (x - 32, self.scanline + 1)
+        } else {
+            (x, self.scanline)
+        };
+
+        let threshold = (self.split_control & 0x1F) as usize;
+        let inside = if self.split_control.bit(6) {
+            x >= threshold
+        } else {
+            x < threshold
+        };
+        let split = if self.split_control.bit(7)
+            && inside
+            && x < 32
+            && matches!(
+                self.exram_mode,
+                ExRamMode::Nametable | ExRamMode::ExtendedAttribute
+            ) {
+            Some(((scanline + self.split_scroll as usize) % 240) / 8)
+        } else {
+            None
+        };
+        self.tile = Tile {
+            x,
+            extended: self.exram[i & 0x03FF],
+            split,
+            split_fine_y: ((scanline + self.split_scroll as usize) % 240) % 8,
+        };
+    }
+
+    fn put_character_bank(&mut self, i: usize, v: u8) {
+        let n = v as usize | self.character_upper << 8;
+        match i {
+            0x5120..=0x5127 => {
+                self.object_banks[i - 0x5120] = n;
+                self.background_written = false;
+            }
+            _ => {
+                self.background_banks[i - 0x5128] = n;
+                self.background_written = true;
+            }
+        }
+    }
+}
+
+impl RAM<usize> for MMC5 {}
+
+impl ROM<usize> for MMC5 {
+    type Output = u8;
+
+    fn get(&self, i: usize) -> Result<Self::Output> {
+        match i {
+            0x5010 => {
+                let mut audio = self.audio.borrow_mut();
+                let v = audio.pcm_status();
+                audio.acknowledge_pcm();
+                Ok(v)
+            }
+            0x5015 => Ok(self.audio.borrow().status()),
+            0x5204 => {
+                let v = (self.irq_pending.get() as u8) << 7 | (self.in_frame as u8) << 6;
+                self.irq_pending.set(false);
+                Ok(v)
+            }
+            0x5205 => Ok((self.multiplicand as u16 * self.multiplier as u16) as u8),
+            0x5206 => Ok(((self.multiplicand as u16 * self.multiplier as u16) >> 8) as u8),
+            0x5C00..=0x5FFF => match self.exram_mode {
+                ExRamMode::ReadWrite | ExRamMode::ReadOnly => self.exram.get(i - 0x5C00),
+                _ => Ok(0),
+            },
+            0x4020..=0x5FFF => Ok(0),
+            0x6000..=0xFFFF => {
+                let (rom, offset) = self.program_offset(i);
+                if rom {
+                    let v = self.program.get(offset)?;
+                    if (0x8000..=0xBFFF).contains(&i) {
+                        self.audio.borrow_mut().read_program(v);
+                    }
+                    Ok(v)
+                } else {
+                    self.ram.get(offset)
+                }
+            }
+            _ => Err(e::index_out_of_range(i)),
+        }
+    }
+}
+
+impl WOM<usize> for MMC5 {
+    type Input = u8;
+
+    fn put(&mut self, i: usize, v: Self::Input) -> Result<()> {
+        match i {
+            0x5000..=0x5015 => self.audio.borrow_mut().put(i, v),
+            0x5100 => self.program_mode = v & 0b11,
+            0x5101 => self.character_mode = v & 0b11,
+            0x5102 => self.ram_protect[0] = v,
+            0x5103 => self.ram_protect[1] = v,
+            0x5104 => {
+                self.exram_mode = match v & 0b11 {
+                    0 => ExRamMode::Nametable,
+                    1 => ExRamMode::ExtendedAttribute,
+                    2 => ExRamMode::ReadWrite,
+                    _ => ExRamMode::ReadOnly,
+                }
+            }
+            0x5105 => self.nametable_mapping = v,
+            0x5106 => self.fill_tile = v,
+            0x5107 => self.fill_attribute = (v & 0b11) * 0b0101_0101,
+            0x5113..=0x5117 => self.program_banks[i - 0x5113] = v,
+            0x5120..=0x512B => self.put_character_bank(i, v),
+            0x5130 => self.character_upper = (v & 0b11) as usize,
+            0x5200 => self.split_control = v,
+            0x5201 => self.split_scroll = v,
+            0x5202 => self.split_bank = v as usize,
+            0x5203 => self.irq_target = v,
+            0x5204 => self.irq_enabled = v.bit(7),
+            0x5205 => self.multiplicand = v,
+            0x5206 => self.multiplier = v,
+            0x5C00..=0x5FFF if self.exram_mode != ExRamMode::ReadOnly => {
+                self.exram.put(i - 0x5C00, v)?
+            }
+            0x6000..=0xFFFF => {
+                let (rom, offset) = self.program_offset(i);
+                if !rom && self.ram_writable() {
+                    self.ram.put(offset, v)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+impl Mapper for MMC5 {
+    fn character(&self, i: usize) -> Result<u8> {
+        self.character.get(self.character_offset(i))
+    }
+
+    fn put_character(&mut self, i: usize, v: u8) -> Result<()> {
+        self.character.put(self.character_offset(i), v)
+    }
+
+    fn sprite(&self, i: usize) -> Sprite {
+        let sprite = self.character.sprite(self.background_offset(i));
+        match self.tile.split {
+            Some(_) => sprite.line(self.tile.split_fine_y),
+            None => sprite,
+        }
+    }
+
+    fn object_sprite(&self, i: usize) -> Sprite {
+        self.character.sprite(self.object_offset(i))
+    }
+
+    fn mirroring(&self) -> Option<Mirroring> {
+        None
+    }
+
+    /// 0x5105 selects the page for 0 and 1, the others are answered by `nametable`
+    fn vram_page(&self, slot: usize) -> usize {
+        ((self.nametable_mapping >> (slot * 2)) & 1) as usize
+    }
+
+    fn nametable(&self, i: usize) -> Option<u8> {
+        let offset = i & 0x03FF;
+        let attribute = offset >= 0x03C0;
+
+        // split and extended attributes only affect fetches for rendering
+        if i == self.last_fetch && self.in_frame {
+            if let Some(row) = self.tile.split {
+                let x = self.tile.x;
+                return Some(if attribute {
+                    let v = self.exram[0x03C0 + (row / 4) * 8 + x / 4];
+                    let shift = ((row % 4) / 2) * 4 + ((x % 4) / 2) * 2;
+                    ((v >> shift) & 0b11) * 0b0101_0101
+                } else {
+                    self.exram[row * 32 + x]
+                });
+            }
+            if attribute && self.exram_mode == ExRamMode::ExtendedAttribute {
+                return Some((self.tile.extended >> 6) * 0b0101_0101);
+            }
+        }
+
+        let slot = ((i - 0x2000) / 0x0400) % 4;
+        match (self.nametable_mapping >> (slot * 2)) & 0b11 {
+            2 => Some(match self.exram_mode {
+                ExRamMode::Nametable | ExRamMode::ExtendedAttribute => self.exram[offset],
+                _ => 0,
+            }),
+            3 if attribute => Some(self.fill_attribute),
+            3 => Some(self.fill_tile),
+            // 0 and 1 are the console VRAM pages of `vram_page`
+            _ => None,
+        }
+    }
+
+    fn put_nametable(&mut self, i: usize, v: u8) -> bool {
+        let slot = ((i - 0x2000) / 0x0400) % 4;
+        match (self.nametable_mapping >> (slot * 2)) & 0b11 {
+            2 => {
+                if matches!(
+                    self.exram_mode,
+                    ExRamMode::Nametable | ExRamMode::ExtendedAttribute
+                ) {
+                    self.exram[i & 0x03FF] = v;
+                }
+                true
+            }
+            3 => true,
+            _ => false,
+        }
+    }
+
+    fn fetch(&mut self, i: usize) {
+        if (0x2000..=0x2FFF).contains(&i) {
+            self.detect_scanline(i);
+            if i & 0x03FF < 0x03C0 {
+                self.fetch_name(i);
+            }
+        }
+        self.last_fetch = i;
+    }
+
+    fn idle(&mut self) {
+        self.in_frame = false;
+        self.last_fetch = 0;
+        self.same_fetches = 0;
+    }
+
+    fn ppu_register(&mut self, i: usize, v: u8) {
+        if i == 0 {
+            self.large_sprites = v.bit(5);
+        }
+    }
+
+    fn clock(&mut self) {
+        self.audio.borrow_mut().clock();
+    }
+
+    fn irq(&self) -> bool {
+        (self.irq_pending.get() && self.irq_enabled) || self.audio.borrow().pcm_irq()
+    }
+
+    fn sample(&self) -> f32 {
+        self.audio.borrow().output()
+    }
+
+    fn battery(&self) -> Option<Vec<u8>> {
+        self.battery.then(|| self.ram.clone())
+    }
+
+    fn load_battery(&mut self, data: &[u8]) {
+        battery::restore(&mut self.ram, data);
+    }
+}
+
+#[cfg(test)]
+fn mock() -> MMC5 {
+    let program = (0..0x2000 * 16)
+        .map(|i| (i / 0x2000) as u8)
+        .collect::<Vec<u8>>();
+    let character = (0..0x0400 * 256)
+        .map(|i| (i / 0x0400) as u8)
+        .collect::<Vec<u8>>();
+    MMC5::new(&program, Character::new(&character), false)
+}
+
+#[cfg(test)]
+fn scanline(m: &mut MMC5) {
+    m.fetch(0x2000);
+    m.fetch(0x2000);
+    m.fetch(0x2000);
+    m.fetch(0x23C0);
+    m.fetch(0x0000);
+}
+
+#[test]
+fn it_program_modes() {
+    let mut m = mock();
+    assert_eq!(m.get(0xE000).unwrap(), 15);
+    m.put(0x5114, 0x80 | 3).unwrap();
+    m.put(0x5115, 0x80 | 4).unwrap();
+    assert_eq!(m.get(0x8000).unwrap(), 3);
+    assert_eq!(m.get(0xA000).unwrap(), 4);
+
+    // 16KB banks ignore the lowest bit
+    m.put(0x5100, 1).unwrap();
+    m.put(0x5115, 0x80 | 5).unwrap();
+    assert_eq!(m.get(0x8000).unwrap(), 4);
+    assert_eq!(m.get(0xA000).unwrap(), 5);
+    assert_eq!(m.get(0xC000).unwrap(), 14);
+
+    m.put(0x5100, 0).unwrap();
+    assert_eq!(m.get(0x8000).unwrap(), 12);
+}
+
+#[test]
+fn it_ram_protect() {
+    let mut m = mock();
+    m.put(0x6000, 0x55).unwrap();
+    assert_eq!(m.get(0x6000).unwrap(), 0);
+    m.put(0x5102, 0b10).unwrap();
+    m.put(0x5103, 0b01).unwrap();
+    m.put(0x6000, 0x55).unwrap();
+    assert_eq!(m.get(0x6000).unwrap(), 0x55);
+    // the same RAM bank in the ROM window
+    m.put(0x5114, 0x00).unwrap();
+    assert_eq!(m.get(0x8000).unwrap(), 0x55);
+    m.put(0x8000, 0xAA).unwrap();
+    assert_eq!(m.get(0x6000).unwrap(), 0xAA);
+}
+
+#[test]
+fn it_character_sets() {
+    let mut m = mock();
+    m.put(0x5101, 3).unwrap();
+    m.put(0x5120, 0x10).unwrap();
+    m.put(0x5128, 0x20).unwrap();
+    // 8x8: the latest written set is used for both
+    assert_eq!(m.sprite(0x0000), m.character.sprite(0x20 * 0x0400));
+    assert_eq!(m.object_sprite(0x0000), m.character.sprite(0x20 * 0x0400));
+    // 8x16: separate sets
+    m.ppu_register(0, 0b0010_0000);
+    assert_eq!(m.object_sprite(0x0000), m.character.sprite(0x10 * 0x0400));
+    assert_eq!(m.character(0x1000).unwrap(), 0x20);
+}
+
+#[test]
+fn it_multiplier() {
+    let mut m = mock();
+    m.put(0x5205, 200).unwrap();
+    m.put(0x5206, 100).unwrap();
+    assert_eq!(m.get(0x5205).unwrap(), (20000 & 0xFF) as u8);
+    assert_eq!(m.get(0x5206).unwrap(), (20000 >> 8) as u8);
+}
+
+#[test]
+fn it_fill_mode() {
+    let mut m = mock();
+    m.put(0x5105, 0b11_10_01_00).unwrap();
+    m.put(0x5106, 0x42).unwrap();
+    m.put(0x5107, 0x02).unwrap();
+    assert_eq!(m.nametable(0x2000), None);
+    assert_eq!(m.nametable(0x2C00), Some(0x42));
+    assert_eq!(m.nametable(0x2FC0), Some(0xAA));
+    m.put(0x5C05, 0x33).unwrap();
+    assert_eq!(m.nametable(0x2805), Some(0x33));
+    assert_eq!((m.vram_page(0), m.vram_page(1)), (0, 1));
+    // both console VRAM slots on the page 0
+    m.put(0x5105, 0xA0).unwrap();
+    assert_eq!((m.vram_page(0), m.vram_page(1)), (0, 0));
+}
+
+#[test]
+fn it_scanline_irq() {
+    let mut m = mock();
+    m.put(0x5203, 2).unwrap();
+    m.put(0x5204, 0x80).unwrap();
+    scanline(&mut m);
+    assert_eq!(m.get(0x5204).unwrap(), 0b0100_0000);
+    scanline(&mut m);
+    assert!(!m.irq());
+    scanline(&mut m);
+    assert!(m.irq());
+    assert_eq!(m.get(0x5204).unwrap(), 0b1100_0000);
+    assert!(!m.irq());
+    m.idle();
+    assert_eq!(m.get(0x5204).unwrap(), 0);
+}
+
+#[test]
+fn it_extended_attribute() {
+    let mut m = mock();
+    m.put(0x5104, 1).unwrap();
+    m.put(0x5C00, 0b1100_0011).unwrap();
+    scanline(&mut m);
+    m.fetch(0x2000);
+    m.fetch(0x23C0);
+    assert_eq!(m.nametable(0x23C0), Some(0xFF));
+    assert_eq!(m.sprite(0x0000), m.character.sprite(3 * 0x1000));
+}
+
+#[test]
+fn it_vertical_split() {
+    // each byte of CHR is its own offset, so the rows of a tile differ
+    let character = (0..0x0400 * 256).map(|i| i as u8).collect::<Vec<u8>>();
+    let mut m = MMC5::new(&[0; 0x2000], Character::new(&character), false);
+    m.put(0x5200, 0x80 | 0x1F).unwrap();
+    m.put(0x5201, 3).unwrap();
+    m.put(0x5202, 1).unwrap();
+    // `scanline` leaves the next fetch at the fourth tile of the first row
+    m.put(0x5C03, 0x05).unwrap();
+    scanline(&mut m);
+    m.fetch(0x2040);
+    assert_eq!(m.nametable(0x2040), Some(0x05));
+    // the row 3 of the tile, whatever fine Y the PPU has
+    let tile = m.character.sprite(0x1000);
+    assert!(m
+        .sprite(0x1000)
+        .bits()
+        .iter()
+        .all(|row| *row == tile.bits()[3]));
+
+    // the scroll 5 on the line 1 is the row 6
+    m.put(0x5201, 5).unwrap();
+    m.fetch(0x0000);
+    scanline(&mut m);
+    m.fetch(0x2040);
+    assert!(m
+        .sprite(0x1000)
+        .bits()
+        .iter()
+        .all(|row| *row == tile.bits()[6]));
+}
@@ -0,0 +1,227 @@
+use crate::bits::Byte;
+
+#[rustfmt::skip]
+const LENGTH_TABLE: [u8; 32] = [
+    10, 254, 20, 2, 40, 4, 80, 6, 160, 8, 60, 10, 14, 12, 26, 14,
+    12, 16, 24, 18, 48, 20, 96, 22, 192, 24, 72, 26, 16, 28, 32, 30,
+];
+
+const DUTY_TABLE: [[u8; 8]; 4] = [
+    [0, 1, 0, 0, 0, 0, 0, 0],
+    [0, 1, 1, 0, 0, 0, 0, 0],
+    [0, 1, 1, 1, 1, 0, 0, 0],
+    [1, 0, 0, 1, 1, 1, 1, 1],
+];
+
+/// envelope and length counter are clocked at 240Hz regardless of the APU frame counter
+const QUARTER_FRAME: usize = 7457;
+
+/// the same as the APU pulse without the sweep unit
+#[derive(Debug, Default)]
+struct Pulse {
+    enabled: bool,
+    duty: usize,
+    step: usize,
+    period: u16,
+    timer: u16,
+    /// also envelope loop
+    halt: bool,
+    constant: bool,
+    volume: u8,
+    envelope_start: bool,
+    envelope_divider: u8,
+    envelope_decay: u8,
+    length: u8,
+}
+
+impl Pulse {
+    fn put(&mut self, register: usize, v: u8) {
+        match register {
+            0 => {
+                self.duty = (v >> 6) as usize;
+                self.halt = v.bit(5);
+                self.constant = v.bit(4);
+                self.volume = v & 0x0F;
+            }
+            2 => self.period = (self.period & 0x0700) | v as u16,
+            3 => {
+                self.period = (self.period & 0x00FF) | ((v & 0b111) as u16) << 8;
+                if self.enabled {
+                    self.length = LENGTH_TABLE[(v >> 3) as usize];
+                }
+                self.step = 0;
+                self.envelope_start = true;
+            }
+            _ => {}
+        }
+    }
+
+    fn set_enabled(&mut self, v: bool) {
+        self.enabled = v;
+        if !v {
+            self.length = 0;
+        }
+    }
+
+    /// every APU cycle (2 CPU cycles)
+    fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.period;
+            self.step = (self.step + 1) % 8;
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    fn clock_quarter_frame(&mut self) {
+        if self.envelope_start {
+            self.envelope_start = false;
+            self.envelope_decay = 15;
+            self.envelope_divider = self.volume;
+        } else if self.envelope_divider == 0 {
+            self.envelope_divider = self.volume;
+            if self.envelope_decay > 0 {
+                self.envelope_decay -= 1;
+            } else if self.halt {
+                self.envelope_decay = 15;
+            }
+        } else {
+            self.envelope_divider -= 1;
+        }
+
+        if !self.halt && self.length > 0 {
+            self.length -= 1;
+        }
+    }
+
+    fn output(&self) -> u8 {
+        // unlike the APU, MMC5 pulses are not muted by small periods
+        if self.length == 0 || DUTY_TABLE[self.duty][self.step] == 0 {
+            0
+        } else if self.constant {
+            self.volume
+        } else {
+            self.envelope_decay
+        }
+    }
+}
+
+/// two pulse channels and an 8 bit PCM channel of MMC5
+#[derive(Debug, Default)]
+pub struct Mmc5Audio {
+    pulses: [Pulse; 2],
+    pcm: u8,
+    /// 0x5010 bit 0, PCM is fed by reads of 0x8000～0xBFFF
+    pcm_read_mode: bool,
+    pcm_irq_enabled: bool,
+    pcm_irq: bool,
+    cycle: usize,
+}
+
+impl Mmc5Audio {
+    /// 0x5000～0x5015
+    pub fn put(&mut self, i: usize, v: u8) {
+        match i {
+            0x5000..=0x5003 => self.pulses[0].put(i - 0x5000, v),
+            0x5004..=0x5007 => self.pulses[1].put(i - 0x5004, v),
+            0x5010 => {
+                self.pcm_read_mode = v.bit(0);
+                self.pcm_irq_enabled = v.bit(7);
+            }
+            0x5011 if !self.pcm_read_mode => self.put_pcm(v),
+            0x5015 => {
+                self.pulses[0].set_enabled(v.bit(0));
+                self.pulses[1].set_enabled(v.bit(1));
+            }
+            _ => {}
+        }
+    }
+
+    /// CPU reads of 0x8000～0xBFFF in PCM read mode
+    pub fn read_program(&mut self, v: u8) {
+        if self.pcm_read_mode {
+            self.put_pcm(v);
+        }
+    }
+
+    /// value 0 does not change the output, it raises the IRQ instead
+    fn put_pcm(&mut self, v: u8) {
+        if v == 0 {
+            self.pcm_irq = true;
+        } else {
+            self.pcm = v;
+        }
+    }
+
+    /// 0x5010
+    pub fn pcm_status(&self) -> u8 {
+        let irq = self.pcm_irq && self.pcm_irq_enabled;
+        (irq as u8) << 7 | self.pcm_read_mode as u8
+    }
+
+    pub fn acknowledge_pcm(&mut self) {
+        self.pcm_irq = false;
+    }
+
+    pub fn pcm_irq(&self) -> bool {
+        self.pcm_irq && self.pcm_irq_enabled
+    }
+
+    /// 0x5015
+    pub fn status(&self) -> u8 {
+        ((self.pulses[1].length > 0) as u8) << 1 | (self.pulses[0].length > 0) as u8
+    }
+
+    pub fn clock(&mut self) {
+        self.cycle += 1;
+        if self.cycle & 1 == 0 {
+            for pulse in self.pulses.iter_mut() {
+                pulse.clock_timer();
+            }
+        }
+        if self.cycle == QUARTER_FRAME {
+            self.cycle = 0;
+            for pulse in self.pulses.iter_mut() {
+                pulse.clock_quarter_frame();
+            }
+        }
+    }
+
+    /// 0.0～1.0, pulses use the same non-linear mixing as the APU
+    pub fn output(&self) -> f32 {
+        let pulse = (self.pulses[0].output() + self.pulses[1].output()) as f32;
+        let pulse = if pulse == 0.0 {
+            0.0
+        } else {
+            95.88 / (8128.0 / pulse + 100.0)
+        };
+        pulse + self.pcm as f32 / 255.0 * 0.25
+    }
+}
+
+#[test]
+fn it_length_and_status() {
+    let mut audio = Mmc5Audio::default();
+    audio.put(0x5003, 0b0000_1000);
+    // disabled channels do not load the length counter
+    assert_eq!(audio.status(), 0);
+    audio.put(0x5015, 0b11);
+    audio.put(0x5003, 0b0000_1000);
+    audio.put(0x5007, 0b0000_1000);
+    assert_eq!(audio.status(), 0b11);
+    audio.put(0x5015, 0b10);
+    assert_eq!(audio.status(), 0b10);
+}
+
+#[test]
+fn it_pcm() {
+    let mut audio = Mmc5Audio::default();
+    audio.put(0x5011, 0x80);
+    assert!(audio.output() > 0.0);
+    audio.put(0x5010, 0b1000_0001);
+    audio.read_program(0x00);
+    assert!(audio.pcm_irq());
+    assert_eq!(audio.pcm_status(), 0b1000_0001);
+    audio.acknowledge_pcm();
+    assert!(!audio.pcm_irq());
+}
@@ -0,0 +1,23 @@
+mod bank;
+mod battery;
+mod cartridge;
+mod character;
+mod fds;
+mod fds_audio;
+mod fme7;
+mod mapper;
+mod mmc2;
+mod mmc5;
+mod mmc5_audio;
+mod namco163;
+mod namco163_audio;
+mod nrom;
+mod sunsoft5b_audio;
+mod uxrom;
+mod vrc;
+mod vrc6;
+mod vrc6_audio;
+mod vrc_irq;
+
+pub use cartridge::{load, load_fds, mapper_name, Cartridge};
+pub use mapper::{Mapper, Mirroring};
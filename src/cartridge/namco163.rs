@@ -0,0 +1,329 @@
+use super::bank;
+use super::character::Character;
+use super::mapper::{Mapper, Mirroring};
+use super::namco163_audio::{Namco163Audio, INTERNAL_RAM_LENGTH};
+use crate::bits::Byte;
+use crate::memory::{RAM, ROM, WOM};
+use crate::result::{e, Result};
+use crate::sprite::Sprite;
+use std::cell::Cell;
+
+/// bank numbers from 0xE0 select the console VRAM instead of CHR ROM
+const VRAM_BANK: u8 = 0xE0;
+
+/// mapper 19 (Namco 163 / 129)
+pub struct Namco163 {
+    program: Vec<u8>,
+    character: Character,
+    ram: Vec<u8>,
+    battery: bool,
+    /// 8KB banks at 0x8000, 0xA000, 0xC000
+    program_banks: [usize; 3],
+    /// 1KB banks
+    character_banks: [u8; 8],
+    /// 0xE800 bit 6 and 7, banks from VRAM_BANK stay CHR ROM at 0x0000～0x0FFF and 0x1000～0x1FFF
+    character_rom_only: [bool; 2],
+    /// 1KB banks of 0x2000, 0x2400, 0x2800, 0x2C00
+    nametable_banks: [u8; 4],
+    /// 0xF800 bit 7, the address is incremented after each access
+    auto_increment: bool,
+    /// 0xF800 bit 0～6, Cell because reads also increment it
+    address: Cell<u8>,
+    /// 0xF800 bit 4～7 must be 0100 to write PRG RAM, bit 0～3 protect each 2KB
+    write_protect: u8,
+    sound_disabled: bool,
+    /// 15 bit
+    counter: u16,
+    irq_enabled: bool,
+    irq: bool,
+    audio: Namco163Audio,
+}
+
+impl Namco163 {
+    pub fn new(program: &[u8], character: Character, battery: bool) -> Self {
+        Self {
+            program: program.to_vec(),
+            character,
+            ram: vec![0; 0x2000],
+            battery,
+            program_banks: [0; 3],
+            character_banks: [0; 8],
+            character_rom_only: [false; 2],
+            nametable_banks: [VRAM_BANK, VRAM_BANK + 1, VRAM_BANK, VRAM_BANK + 1],
+            auto_increment: false,
+            address: Cell::new(0),
+            write_protect: 0,
+            sound_disabled: false,
+            counter: 0,
+            irq_enabled: false,
+            irq: false,
+            audio: Namco163Audio::default(),
+        }
+    }
+
+    fn program_offset(&self, i: usize) -> usize {
+        let len = self.program.len();
+        let n = match i {
+            0xE000..=0xFFFF => bank::last(len, 0x2000, 0),
+            _ => bank::offset(len, 0x2000, self.program_banks[(i - 0x8000) / 0x2000]),
+        };
+        n + (i & 0x1FFF)
+    }
+
+    fn character_offset(&self, i: usize) -> usize {
+        let n = self.character_banks[i / 0x0400] as usize;
+        bank::offset(self.character.len(), 0x0400, n) + (i & 0x03FF)
+    }
+
+    fn ram_writable(&self, i: usize) -> bool {
+        self.write_protect >> 4 == 0b0100 && !self.write_protect.bit((i - 0x6000) / 0x0800)
+    }
+
+    /// address of the internal RAM for the data port
+    fn access(&self) -> usize {
+        let address = self.address.get();
+        if self.auto_increment {
+            self.address.set((address + 1) & 0x7F);
+        }
+        address as usize
+    }
+}
+
+impl RAM<usize> for Namco163 {}
+
+impl ROM<usize> for Namco163 {
+    type Output = u8;
+
+    fn get(&self, i: usize) -> Result<Self::Output> {
+        match i {
+            _ if (0x4800..=0x4FFF).contains(&i) => Ok(self.audio.ram[self.access()]),
+            _ if (0x5000..=0x57FF).contains(&i) => Ok(self.counter as u8),
+            _ if (0x5800..=0x5FFF).contains(&i) => {
+                Ok((self.counter >> 8) as u8 | (self.irq_enabled as u8) << 7)
+            }
+            _ if (0x4020..=0x5FFF).contains(&i) => Ok(0),
+            _ if (0x6000..=0x7FFF).contains(&i) => self.ram.get(i - 0x6000),
+            _ if (0x8000..=0xFFFF).contains(&i) => self.program.get(self.program_offset(i)),
+            _ => Err(e::index_out_of_range(i)),
+        }
+    }
+}
+
+impl WOM<usize> for Namco163 {
+    type Input = u8;
+
+    fn put(&mut self, i: usize, v: Self::Input) -> Result<()> {
+        match i {
+            _ if (0x4800..=0x4FFF).contains(&i) => {
+                let address = self.access();
+                self.audio.ram[address] = v;
+            }
+            _ if (0x5000..=0x57FF).contains(&i) => {
+                self.counter = (self.counter & 0x7F00) | v as u16;
+                self.irq = false;
+            }
+            _ if (0x5800..=0x5FFF).contains(&i) => {
+                self.counter = (self.counter & 0x00FF) | ((v & 0x7F) as u16) << 8;
+                self.irq_enabled = v.bit(7);
+                self.irq = false;
+            }
+            _ if (0x6000..=0x7FFF).contains(&i) => {
+                if self.ram_writable(i) {
+                    self.ram.put(i - 0x6000, v)?;
+                }
+            }
+            _ if (0x8000..=0xBFFF).contains(&i) => {
+                self.character_banks[(i - 0x8000) / 0x0800] = v;
+            }
+            _ if (0xC000..=0xDFFF).contains(&i) => {
+                self.nametable_banks[(i - 0xC000) / 0x0800] = v;
+            }
+            _ if (0xE000..=0xE7FF).contains(&i) => {
+                self.program_banks[0] = (v & 0x3F) as usize;
+                self.sound_disabled = v.bit(6);
+            }
+            _ if (0xE800..=0xEFFF).contains(&i) => {
+                self.program_banks[1] = (v & 0x3F) as usize;
+                self.character_rom_only = [v.bit(6), v.bit(7)];
+            }
+            _ if (0xF000..=0xF7FF).contains(&i) => self.program_banks[2] = (v & 0x3F) as usize,
+            _ if (0xF800..=0xFFFF).contains(&i) => {
+                self.auto_increment = v.bit(7);
+                self.address.set(v & 0x7F);
+                self.write_protect = v;
+            }
+            _ => return Err(e::readonly(i)),
+        }
+        Ok(())
+    }
+}
+
+impl Mapper for Namco163 {
+    fn character(&self, i: usize) -> Result<u8> {
+        self.character.get(self.character_offset(i))
+    }
+
+    fn put_character(&mut self, i: usize, v: u8) -> Result<()> {
+        self.character.put(self.character_offset(i), v)
+    }
+
+    fn sprite(&self, i: usize) -> Sprite {
+        self.character.sprite(self.character_offset(i))
+    }
+
+    /// banks from VRAM_BANK select the console VRAM page by bit 0
+    fn character_vram_page(&self, i: usize) -> Option<usize> {
+        let n = self.character_banks[i / 0x0400];
+        (n >= VRAM_BANK && !self.character_rom_only[i / 0x1000]).then_some((n & 1) as usize)
+    }
+
+    fn nametable(&self, i: usize) -> Option<u8> {
+        let n = self.nametable_banks[((i - 0x2000) / 0x0400) % 4];
+        if n >= VRAM_BANK {
+            return None;
+        }
+        let offset = bank::offset(self.character.len(), 0x0400, n as usize) + (i & 0x03FF);
+        self.character.get(offset).ok()
+    }
+
+    fn put_nametable(&mut self, i: usize, _v: u8) -> bool {
+        // writes to CHR ROM are ignored
+        self.nametable_banks[((i - 0x2000) / 0x0400) % 4] < VRAM_BANK
+    }
+
+    fn mirroring(&self) -> Option<Mirroring> {
+        None
+    }
+
+    /// banks from VRAM_BANK select the page by bit 0
+    fn vram_page(&self, slot: usize) -> usize {
+        (self.nametable_banks[slot] & 1) as usize
+    }
+
+    fn clock(&mut self) {
+        if self.irq_enabled && self.counter < 0x7FFF {
+            self.counter += 1;
+            if self.counter == 0x7FFF {
+                self.irq = true;
+            }
+        }
+        self.audio.clock();
+    }
+
+    fn irq(&self) -> bool {
+        self.irq
+    }
+
+    fn sample(&self) -> f32 {
+        if self.sound_disabled {
+            0.0
+        } else {
+            self.audio.output()
+        }
+    }
+
+    fn battery(&self) -> Option<Vec<u8>> {
+        if !self.battery {
+            return None;
+        }
+        let mut data = self.ram.clone();
+        data.extend_from_slice(&self.audio.ram);
+        Some(data)
+    }
+
+    fn load_battery(&mut self, data: &[u8]) {
+        let (ram, internal) = data.split_at(data.len().min(self.ram.len()));
+        self.ram[..ram.len()].copy_from_slice(ram);
+        let internal = &internal[..internal.len().min(INTERNAL_RAM_LENGTH)];
+        self.audio.ram[..internal.len()].copy_from_slice(internal);
+    }
+}
+
+#[cfg(test)]
+fn mock() -> Namco163 {
+    let program = (0..0x2000 * 16)
+        .map(|i| (i / 0x2000) as u8)
+        .collect::<Vec<u8>>();
+    let character = (0..0x0400 * 256)
+        .map(|i| (i / 0x0400) as u8)
+        .collect::<Vec<u8>>();
+    Namco163::new(&program, Character::new(&character), true)
+}
+
+#[test]
+fn it_banks() {
+    let mut m = mock();
+    m.put(0xE000, 3).unwrap();
+    m.put(0xE800, 4).unwrap();
+    m.put(0xF000, 5).unwrap();
+    assert_eq!(m.get(0x8000).unwrap(), 3);
+    assert_eq!(m.get(0xA000).unwrap(), 4);
+    assert_eq!(m.get(0xC000).unwrap(), 5);
+    assert_eq!(m.get(0xE000).unwrap(), 15);
+    m.put(0x9800, 0x42).unwrap();
+    assert_eq!(m.character(0x0C00).unwrap(), 0x42);
+}
+
+#[test]
+fn it_character_vram() {
+    let mut m = mock();
+    m.put(0x8000, VRAM_BANK + 1).unwrap();
+    m.put(0xB800, 0xFF).unwrap();
+    assert_eq!(m.character_vram_page(0x0005), Some(1));
+    assert_eq!(m.character_vram_page(0x1C05), Some(1));
+    assert_eq!(m.character_vram_page(0x0405), None);
+    // CHR ROM for the upper pattern table
+    m.put(0xE800, 0x80).unwrap();
+    assert_eq!(m.character_vram_page(0x0005), Some(1));
+    assert_eq!(m.character_vram_page(0x1C05), None);
+    assert_eq!(m.character(0x1C05).unwrap(), 0xFF);
+}
+
+#[test]
+fn it_nametable() {
+    let mut m = mock();
+    assert_eq!(m.nametable(0x2000), None);
+    assert_eq!(
+        (0..4).map(|i| m.vram_page(i)).collect::<Vec<_>>(),
+        vec![0, 1, 0, 1]
+    );
+    m.put(0xC800, 0x12).unwrap();
+    assert_eq!(m.nametable(0x2400), Some(0x12));
+    assert!(m.put_nametable(0x2400, 0));
+    assert!(!m.put_nametable(0x2800, 0));
+    // the pages are selected for each nametable
+    m.put(0xD000, VRAM_BANK + 1).unwrap();
+    m.put(0xD800, VRAM_BANK).unwrap();
+    assert_eq!((m.vram_page(2), m.vram_page(3)), (1, 0));
+}
+
+#[test]
+fn it_internal_ram() {
+    let mut m = mock();
+    m.put(0xF800, 0x80 | 0x10).unwrap();
+    m.put(0x4800, 0x01).unwrap();
+    m.put(0x4800, 0x02).unwrap();
+    m.put(0xF800, 0x80 | 0x10).unwrap();
+    assert_eq!(m.get(0x4800).unwrap(), 0x01);
+    assert_eq!(m.get(0x4800).unwrap(), 0x02);
+
+    let data = m.battery().unwrap();
+    assert_eq!(data.len(), 0x2000 + INTERNAL_RAM_LENGTH);
+    let mut other = mock();
+    other.load_battery(&data);
+    assert_eq!(other.audio.ram[0x11], 0x02);
+}
+
+#[test]
+fn it_irq() {
+    let mut m = mock();
+    m.put(0x5000, 0xFE).unwrap();
+    m.put(0x5800, 0x80 | 0x7F).unwrap();
+    m.clock();
+    assert!(m.irq());
+    // stops at 0x7FFF
+    m.clock();
+    assert_eq!(m.get(0x5000).unwrap(), 0xFF);
+    m.put(0x5800, 0x00).unwrap();
+    assert!(!m.irq());
+}
@@ -0,0 +1,113 @@
+pub const INTERNAL_RAM_LENGTH: usize = 0x80;
+
+/// one channel is updated every 15 CPU cycles
+const CHANNEL_CYCLES: usize = 15;
+
+/// Namco 163 wavetable channels.
+/// samples and channel registers share 128 bytes of internal RAM,
+/// registers of channel n are at 0x40 + 8 * n.
+/// the chip outputs only one channel at a time and switches them in turn.
+#[derive(Debug)]
+pub struct Namco163Audio {
+    pub ram: [u8; INTERNAL_RAM_LENGTH],
+    /// channel being updated, counts down from 7
+    channel: usize,
+    /// output of each channel, -8 * 15 ～ 7 * 15
+    outputs: [i8; 8],
+    cycle: usize,
+}
+
+impl Default for Namco163Audio {
+    fn default() -> Self {
+        Self {
+            ram: [0; INTERNAL_RAM_LENGTH],
+            channel: 7,
+            outputs: [0; 8],
+            cycle: 0,
+        }
+    }
+}
+
+impl Namco163Audio {
+    /// 1～8, from 0x7F bit 4～6
+    fn enabled_channels(&self) -> usize {
+        ((self.ram[0x7F] >> 4) & 0b111) as usize + 1
+    }
+
+    /// 4 bit sample of the wavetable, lower nibble first
+    fn sample(&self, n: usize) -> u8 {
+        let v = self.ram[(n / 2) % INTERNAL_RAM_LENGTH];
+        if n & 1 == 1 {
+            v >> 4
+        } else {
+            v & 0x0F
+        }
+    }
+
+    fn update(&mut self, channel: usize) {
+        let base = 0x40 + channel * 8;
+        let r = &self.ram[base..base + 8];
+        let frequency = r[0] as u32 | (r[2] as u32) << 8 | ((r[4] & 0b11) as u32) << 16;
+        let length = (256 - (r[4] & 0xFC) as u32) << 16;
+        let phase = r[1] as u32 | (r[3] as u32) << 8 | (r[5] as u32) << 16;
+        let offset = r[6] as usize;
+        let volume = (r[7] & 0x0F) as i8;
+
+        let phase = (phase + frequency) % length;
+        let sample = self.sample((phase >> 16) as usize + offset) as i8;
+        self.outputs[channel] = (sample - 8) * volume;
+
+        self.ram[base + 1] = phase as u8;
+        self.ram[base + 3] = (phase >> 8) as u8;
+        self.ram[base + 5] = (phase >> 16) as u8;
+    }
+
+    pub fn clock(&mut self) {
+        self.cycle += 1;
+        if self.cycle < CHANNEL_CYCLES {
+            return;
+        }
+        self.cycle = 0;
+        let first = 8 - self.enabled_channels();
+        self.channel = if self.channel <= first {
+            7
+        } else {
+            self.channel - 1
+        };
+        self.update(self.channel);
+    }
+
+    /// 0.0～1.0, output of the current channel
+    pub fn output(&self) -> f32 {
+        (self.outputs[self.channel] as f32 + 120.0) / 240.0
+    }
+}
+
+#[test]
+fn it_sample() {
+    let mut audio = Namco163Audio::default();
+    audio.ram[0] = 0x3A;
+    assert_eq!(audio.sample(0), 0x0A);
+    assert_eq!(audio.sample(1), 0x03);
+}
+
+#[test]
+fn it_channel() {
+    let mut audio = Namco163Audio::default();
+    // wave of 4 samples: 0, F, 0, F
+    audio.ram[0] = 0xF0;
+    audio.ram[1] = 0xF0;
+    let base = 0x40 + 7 * 8;
+    // one sample per update
+    audio.ram[base + 4] = 0xFC | 0x01;
+    audio.ram[base + 7] = 0x0F;
+    for _ in 0..CHANNEL_CYCLES {
+        audio.clock();
+    }
+    assert_eq!(audio.channel, 7);
+    assert_eq!(audio.outputs[7], 7 * 15);
+    for _ in 0..CHANNEL_CYCLES {
+        audio.clock();
+    }
+    assert_eq!(audio.outputs[7], -8 * 15);
+}
@@ -0,0 +1,88 @@
+use super::battery;
+use super::character::Character;
+use super::mapper::{Mapper, Mirroring};
+use crate::memory::{RAM, ROM, WOM};
+use crate::result::{e, Result};
+use crate::sprite::Sprite;
+
+/// mapper 0
+/// 16KB or 32KB PRG ROM, 8KB CHR ROM, no bank switching.
+pub struct NROM {
+    program: Vec<u8>,
+    character: Character,
+    ram: Vec<u8>,
+    battery: bool,
+    mirroring: Mirroring,
+}
+
+impl NROM {
+    pub fn new(
+        program: &[u8],
+        character: Character,
+        mirroring: Mirroring,
+        battery: bool,
+    ) -> Self {
+        Self {
+            program: program.to_vec(),
+            character,
+            ram: vec![0; 0x2000],
+            battery,
+            mirroring,
+        }
+    }
+}
+
+impl RAM<usize> for NROM {}
+
+impl ROM<usize> for NROM {
+    type Output = u8;
+
+    fn get(&self, i: usize) -> Result<Self::Output> {
+        match i {
+            _ if (0x4020..=0x5FFF).contains(&i) => Ok(0),
+            _ if (0x6000..=0x7FFF).contains(&i) => self.ram.get(i - 0x6000),
+            // 16KB ROM is mirrored into 0xC000～0xFFFF
+            _ if (0x8000..=0xFFFF).contains(&i) => {
+                self.program.get((i - 0x8000) % self.program.len())
+            }
+            _ => Err(e::index_out_of_range(i)),
+        }
+    }
+}
+
+impl WOM<usize> for NROM {
+    type Input = u8;
+
+    fn put(&mut self, i: usize, v: Self::Input) -> Result<()> {
+        match i {
+            _ if (0x6000..=0x7FFF).contains(&i) => self.ram.put(i - 0x6000, v),
+            _ => Err(e::readonly(i)),
+        }
+    }
+}
+
+impl Mapper for NROM {
+    fn character(&self, i: usize) -> Result<u8> {
+        self.character.get(i)
+    }
+
+    fn put_character(&mut self, i: usize, v: u8) -> Result<()> {
+        self.character.put(i, v)
+    }
+
+    fn sprite(&self, i: usize) -> Sprite {
+        self.character.sprite(i)
+    }
+
+    fn mirroring(&self) -> Option<Mirroring> {
+        Some(self.mirroring)
+    }
+
+    fn battery(&self) -> Option<Vec<u8>> {
+        self.battery.then(|| self.ram.clone())
+    }
+
+    fn load_battery(&mut self, data: &[u8]) {
+        battery::restore(&mut self.ram, data);
+    }
+}
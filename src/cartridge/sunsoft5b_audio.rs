@@ -0,0 +1,275 @@
+use crate::bits::Byte;
+
+/// tone and noise are clocked every 16 CPU cycles
+const TONE_DIVIDER: usize = 16;
+/// envelope of 5B has 32 steps, twice as fast as AY-3-8910
+const ENVELOPE_DIVIDER: usize = 8;
+
+#[derive(Debug, Default)]
+struct Tone {
+    period: u16,
+    counter: u16,
+    high: bool,
+}
+
+impl Tone {
+    fn clock(&mut self) {
+        self.counter += 1;
+        if self.counter >= self.period {
+            self.counter = 0;
+            self.high = !self.high;
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Noise {
+    period: u8,
+    counter: u8,
+    /// 17 bit LFSR
+    shift: u32,
+}
+
+impl Default for Noise {
+    fn default() -> Self {
+        Self {
+            period: 0,
+            counter: 0,
+            shift: 1,
+        }
+    }
+}
+
+impl Noise {
+    fn clock(&mut self) {
+        self.counter += 1;
+        if self.counter >= self.period {
+            self.counter = 0;
+            let feedback = (self.shift ^ (self.shift >> 3)) & 1;
+            self.shift = (self.shift >> 1) | (feedback << 16);
+        }
+    }
+
+    fn high(&self) -> bool {
+        self.shift & 1 == 1
+    }
+}
+
+#[derive(Debug, Default)]
+struct Envelope {
+    period: u16,
+    counter: u16,
+    continued: bool,
+    attack: bool,
+    alternate: bool,
+    hold: bool,
+    /// 0～31
+    step: u8,
+    holding: bool,
+    rising: bool,
+}
+
+impl Envelope {
+    fn put_shape(&mut self, v: u8) {
+        self.continued = v.bit(3);
+        self.attack = v.bit(2);
+        self.alternate = v.bit(1);
+        self.hold = v.bit(0);
+        self.counter = 0;
+        self.step = 0;
+        self.holding = false;
+        self.rising = self.attack;
+    }
+
+    fn clock(&mut self) {
+        self.counter += 1;
+        if self.counter < self.period {
+            return;
+        }
+        self.counter = 0;
+        if self.holding {
+            return;
+        }
+        if self.step < 31 {
+            self.step += 1;
+            return;
+        }
+        // end of a cycle
+        if !self.continued {
+            self.holding = true;
+            self.rising = false;
+        } else if self.hold {
+            self.holding = true;
+            if self.alternate {
+                self.rising = !self.rising;
+            }
+        } else {
+            self.step = 0;
+            if self.alternate {
+                self.rising = !self.rising;
+            }
+        }
+    }
+
+    /// 0～31
+    fn level(&self) -> u8 {
+        if self.holding && !self.continued {
+            0
+        } else if self.rising {
+            self.step
+        } else {
+            31 - self.step
+        }
+    }
+}
+
+/// Sunsoft 5B, compatible with AY-3-8910 / YM2149.
+/// three square channels, a noise and an envelope.
+#[derive(Debug, Default)]
+pub struct Sunsoft5bAudio {
+    /// 0xC000, selected register
+    address: u8,
+    tones: [Tone; 3],
+    noise: Noise,
+    envelope: Envelope,
+    /// mixer register, tone disable bits and noise disable bits
+    disable: u8,
+    /// bit 4 is envelope mode
+    volumes: [u8; 3],
+    prescaler: usize,
+}
+
+impl Sunsoft5bAudio {
+    /// 0xC000～0xDFFF
+    pub fn put_address(&mut self, v: u8) {
+        self.address = v;
+    }
+
+    /// 0xE000～0xFFFF
+    pub fn put_data(&mut self, v: u8) {
+        match self.address {
+            r @ (0x00 | 0x02 | 0x04) => {
+                let tone = &mut self.tones[r as usize / 2];
+                tone.period = (tone.period & 0x0F00) | v as u16;
+            }
+            r @ (0x01 | 0x03 | 0x05) => {
+                let tone = &mut self.tones[r as usize / 2];
+                tone.period = (tone.period & 0x00FF) | ((v & 0x0F) as u16) << 8;
+            }
+            0x06 => self.noise.period = v & 0x1F,
+            0x07 => self.disable = v,
+            r @ 0x08..=0x0A => self.volumes[r as usize - 0x08] = v & 0x1F,
+            0x0B => self.envelope.period = (self.envelope.period & 0xFF00) | v as u16,
+            0x0C => self.envelope.period = (self.envelope.period & 0x00FF) | (v as u16) << 8,
+            0x0D => self.envelope.put_shape(v),
+            // 0x0E, 0x0F are I/O ports, or the address is invalid when upper bits are set
+            _ => {}
+        }
+    }
+
+    pub fn clock(&mut self) {
+        self.prescaler += 1;
+        if self.prescaler == ENVELOPE_DIVIDER || self.prescaler == TONE_DIVIDER {
+            self.envelope.clock();
+        }
+        if self.prescaler == TONE_DIVIDER {
+            self.prescaler = 0;
+            for tone in self.tones.iter_mut() {
+                tone.clock();
+            }
+            self.noise.clock();
+        }
+    }
+
+    fn channel(&self, n: usize) -> f32 {
+        let tone = self.tones[n].high || self.disable.bit(n);
+        let noise = self.noise.high() || self.disable.bit(n + 3);
+        if !(tone && noise) {
+            return 0.0;
+        }
+        let volume = self.volumes[n];
+        let level = if volume.bit(4) {
+            self.envelope.level()
+        } else if volume == 0 {
+            0
+        } else {
+            // 4 bit volume steps by 3dB, the same as every second envelope step
+            volume * 2 + 1
+        };
+        Self::amplitude(level)
+    }
+
+    /// 5 bit level to amplitude, 1.5dB per step
+    fn amplitude(level: u8) -> f32 {
+        if level == 0 {
+            0.0
+        } else {
+            10f32.powf(-1.5 * (31 - level) as f32 / 20.0)
+        }
+    }
+
+    /// 0.0～1.0
+    pub fn output(&self) -> f32 {
+        (0..3).map(|n| self.channel(n)).sum::<f32>() / 3.0
+    }
+}
+
+#[test]
+fn it_put_registers() {
+    let mut audio = Sunsoft5bAudio::default();
+    audio.put_address(0x01);
+    audio.put_data(0xFA);
+    audio.put_address(0x00);
+    audio.put_data(0x34);
+    assert_eq!(audio.tones[0].period, 0x0A34);
+    audio.put_address(0x06);
+    audio.put_data(0xFF);
+    assert_eq!(audio.noise.period, 0x1F);
+    audio.put_address(0x09);
+    audio.put_data(0xFF);
+    assert_eq!(audio.volumes[1], 0x1F);
+    // invalid address is ignored
+    audio.put_address(0x12);
+    audio.put_data(0xFF);
+    assert_eq!(audio.tones[1].period, 0);
+}
+
+#[test]
+fn it_tone() {
+    let mut audio = Sunsoft5bAudio::default();
+    audio.put_address(0x00);
+    audio.put_data(2);
+    // tone A only, noise off
+    audio.put_address(0x07);
+    audio.put_data(0b0011_1110);
+    audio.put_address(0x08);
+    audio.put_data(0x0F);
+    assert_eq!(audio.output(), 0.0);
+    for _ in 0..TONE_DIVIDER * 2 {
+        audio.clock();
+    }
+    assert_eq!(audio.output(), 1.0 / 3.0);
+    for _ in 0..TONE_DIVIDER * 2 {
+        audio.clock();
+    }
+    assert_eq!(audio.output(), 0.0);
+}
+
+#[test]
+fn it_envelope() {
+    let mut audio = Sunsoft5bAudio::default();
+    audio.put_address(0x0B);
+    audio.put_data(1);
+    // attack, no continue: rises once then drops to 0
+    audio.put_address(0x0D);
+    audio.put_data(0b0100);
+    assert_eq!(audio.envelope.level(), 0);
+    for _ in 0..ENVELOPE_DIVIDER * 31 {
+        audio.clock();
+    }
+    assert_eq!(audio.envelope.level(), 31);
+    for _ in 0..ENVELOPE_DIVIDER {
+        audio.clock();
+    }
+    assert_eq!(audio.envelope.level(), 0);
+}
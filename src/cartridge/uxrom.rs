@@ -0,0 +1,99 @@
+use super::bank;
+use super::character::Character;
+use super::mapper::{Mapper, Mirroring};
+use crate::memory::{RAM, ROM, WOM};
+use crate::result::{e, Result};
+use crate::sprite::Sprite;
+
+/// mapper 2 (UNROM, UOROM)
+/// 16KB switchable PRG bank and the last 16KB fixed, 8KB CHR RAM.
+/// bus conflicts are not emulated, the written value selects the bank as it is.
+pub struct UxROM {
+    program: Vec<u8>,
+    character: Character,
+    program_bank: usize,
+    mirroring: Mirroring,
+}
+
+impl UxROM {
+    pub fn new(program: &[u8], character: Character, mirroring: Mirroring) -> Self {
+        Self {
+            program: program.to_vec(),
+            character,
+            program_bank: 0,
+            mirroring,
+        }
+    }
+
+    fn program_offset(&self, i: usize) -> usize {
+        let len = self.program.len();
+        match i {
+            _ if (0x8000..=0xBFFF).contains(&i) => {
+                bank::offset(len, 0x4000, self.program_bank) + (i - 0x8000)
+            }
+            _ => bank::last(len, 0x4000, 0) + (i - 0xC000),
+        }
+    }
+}
+
+impl RAM<usize> for UxROM {}
+
+impl ROM<usize> for UxROM {
+    type Output = u8;
+
+    fn get(&self, i: usize) -> Result<Self::Output> {
+        match i {
+            _ if (0x4020..=0x7FFF).contains(&i) => Ok(0),
+            _ if (0x8000..=0xFFFF).contains(&i) => self.program.get(self.program_offset(i)),
+            _ => Err(e::index_out_of_range(i)),
+        }
+    }
+}
+
+impl WOM<usize> for UxROM {
+    type Input = u8;
+
+    fn put(&mut self, i: usize, v: Self::Input) -> Result<()> {
+        match i {
+            _ if (0x8000..=0xFFFF).contains(&i) => {
+                self.program_bank = v as usize;
+                Ok(())
+            }
+            _ => Err(e::readonly(i)),
+        }
+    }
+}
+
+impl Mapper for UxROM {
+    fn character(&self, i: usize) -> Result<u8> {
+        self.character.get(i)
+    }
+
+    fn put_character(&mut self, i: usize, v: u8) -> Result<()> {
+        self.character.put(i, v)
+    }
+
+    fn sprite(&self, i: usize) -> Sprite {
+        self.character.sprite(i)
+    }
+
+    fn mirroring(&self) -> Option<Mirroring> {
+        Some(self.mirroring)
+    }
+}
+
+#[test]
+fn it_program_bank() {
+    let program = (0..0x4000 * 8)
+        .map(|i| (i / 0x4000) as u8)
+        .collect::<Vec<u8>>();
+    let mut m = UxROM::new(&program, Character::ram(0x2000), Mirroring::Vertical);
+    assert_eq!(m.get(0x8000).unwrap(), 0);
+    assert_eq!(m.get(0xC000).unwrap(), 7);
+    m.put(0x8000, 3).unwrap();
+    assert_eq!(m.get(0xBFFF).unwrap(), 3);
+    assert_eq!(m.get(0xFFFF).unwrap(), 7);
+    // banks past the ROM wrap around
+    m.put(0xFFFF, 9).unwrap();
+    assert_eq!(m.get(0x8000).unwrap(), 1);
+}
@@ -0,0 +1,279 @@
+use super::bank;
+use super::battery;
+use super::character::Character;
+use super::mapper::{Mapper, Mirroring};
+use super::vrc_irq::VrcIrq;
+use crate::bits::Byte;
+use crate::memory::{RAM, ROM, WOM};
+use crate::result::{e, Result};
+use crate::sprite::Sprite;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Chip {
+    VRC2,
+    VRC4,
+}
+
+/// CPU address lines connected to the two register select pins of the chip.
+/// each pin may be connected to several lines when the board is unknown,
+/// then both variants of the board work.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Wiring {
+    chip: Chip,
+    pin0: usize,
+    pin1: usize,
+    /// VRC2a ignores the lowest bit of CHR bank numbers
+    character_shift: usize,
+}
+
+impl Wiring {
+    const fn new(chip: Chip, pin0: usize, pin1: usize) -> Self {
+        Self {
+            chip,
+            pin0,
+            pin1,
+            character_shift: 0,
+        }
+    }
+
+    /// mapper number, NES 2.0 submapper number
+    pub fn from(mapper: u16, submapper: u8) -> Option<Self> {
+        Some(match (mapper, submapper) {
+            // VRC4a(A1, A2) / VRC4c(A6, A7)
+            (21, 1) => Self::new(Chip::VRC4, 1 << 1, 1 << 2),
+            (21, 2) => Self::new(Chip::VRC4, 1 << 6, 1 << 7),
+            (21, _) => Self::new(Chip::VRC4, 1 << 1 | 1 << 6, 1 << 2 | 1 << 7),
+            // VRC2a(A1, A0)
+            (22, _) => Self {
+                character_shift: 1,
+                ..Self::new(Chip::VRC2, 1 << 1, 1 << 0)
+            },
+            // VRC4f(A0, A1) / VRC4e(A2, A3) / VRC2b(A0, A1)
+            (23, 1) => Self::new(Chip::VRC4, 1 << 0, 1 << 1),
+            (23, 2) => Self::new(Chip::VRC4, 1 << 2, 1 << 3),
+            (23, 3) => Self::new(Chip::VRC2, 1 << 0, 1 << 1),
+            (23, _) => Self::new(Chip::VRC4, 1 << 0 | 1 << 2, 1 << 1 | 1 << 3),
+            // VRC4b(A1, A0) / VRC4d(A3, A2) / VRC2c(A1, A0)
+            (25, 1) => Self::new(Chip::VRC4, 1 << 1, 1 << 0),
+            (25, 2) => Self::new(Chip::VRC4, 1 << 3, 1 << 2),
+            (25, 3) => Self::new(Chip::VRC2, 1 << 1, 1 << 0),
+            (25, _) => Self::new(Chip::VRC4, 1 << 1 | 1 << 3, 1 << 0 | 1 << 2),
+            _ => return None,
+        })
+    }
+
+    /// CPU address to register address (0x8000, 0x8001, ..., 0xF003)
+    fn register(&self, i: usize) -> usize {
+        let a0 = if i & self.pin0 != 0 { 1 } else { 0 };
+        let a1 = if i & self.pin1 != 0 { 2 } else { 0 };
+        (i & 0xF000) | a1 | a0
+    }
+}
+
+/// mapper 21, 22, 23, 25 (Konami VRC2 / VRC4)
+pub struct VRC {
+    wiring: Wiring,
+    program: Vec<u8>,
+    character: Character,
+    ram: Vec<u8>,
+    battery: bool,
+    program_banks: [usize; 2],
+    /// VRC4 can swap 0x8000 and 0xC000
+    program_swap: bool,
+    /// 1KB banks
+    character_banks: [usize; 8],
+    mirroring: Mirroring,
+    irq: VrcIrq,
+}
+
+impl VRC {
+    pub fn new(wiring: Wiring, program: &[u8], character: Character, battery: bool) -> Self {
+        Self {
+            wiring,
+            program: program.to_vec(),
+            character,
+            ram: vec![0; 0x2000],
+            battery,
+            program_banks: [0, 0],
+            program_swap: false,
+            character_banks: [0; 8],
+            mirroring: Mirroring::Vertical,
+            irq: VrcIrq::default(),
+        }
+    }
+
+    fn program_offset(&self, i: usize) -> usize {
+        let len = self.program.len();
+        let n = match (i - 0x8000) / 0x2000 {
+            0 if self.program_swap => bank::last(len, 0x2000, 1),
+            0 => bank::offset(len, 0x2000, self.program_banks[0]),
+            1 => bank::offset(len, 0x2000, self.program_banks[1]),
+            2 if self.program_swap => bank::offset(len, 0x2000, self.program_banks[0]),
+            2 => bank::last(len, 0x2000, 1),
+            _ => bank::last(len, 0x2000, 0),
+        };
+        n + (i & 0x1FFF)
+    }
+
+    fn character_offset(&self, i: usize) -> usize {
+        let n = self.character_banks[i / 0x0400] >> self.wiring.character_shift;
+        bank::offset(self.character.len(), 0x0400, n) + (i & 0x03FF)
+    }
+
+    fn put_character_bank(&mut self, register: usize, v: u8) {
+        // 0xB000 / 0xB001 => bank 0, 0xB002 / 0xB003 => bank 1, ..., 0xE003 => bank 7
+        let n = ((register >> 12) - 0xB) * 2 + ((register & 0b10) >> 1);
+        let bank = self.character_banks[n];
+        self.character_banks[n] = if register & 1 == 0 {
+            (bank & !0x0F) | (v & 0x0F) as usize
+        } else {
+            (bank & 0x0F) | ((v & 0x1F) as usize) << 4
+        };
+    }
+}
+
+impl RAM<usize> for VRC {}
+
+impl ROM<usize> for VRC {
+    type Output = u8;
+
+    fn get(&self, i: usize) -> Result<Self::Output> {
+        match i {
+            _ if (0x4020..=0x5FFF).contains(&i) => Ok(0),
+            _ if (0x6000..=0x7FFF).contains(&i) => self.ram.get(i - 0x6000),
+            _ if (0x8000..=0xFFFF).contains(&i) => self.program.get(self.program_offset(i)),
+            _ => Err(e::index_out_of_range(i)),
+        }
+    }
+}
+
+impl WOM<usize> for VRC {
+    type Input = u8;
+
+    fn put(&mut self, i: usize, v: Self::Input) -> Result<()> {
+        if (0x6000..=0x7FFF).contains(&i) {
+            return self.ram.put(i - 0x6000, v);
+        }
+        if i < 0x8000 {
+            return Err(e::readonly(i));
+        }
+
+        let vrc4 = self.wiring.chip == Chip::VRC4;
+        match self.wiring.register(i) {
+            0x8000..=0x8003 => self.program_banks[0] = (v & 0x1F) as usize,
+            0x9000..=0x9003 if !vrc4 => {
+                self.mirroring = if v.bit(0) {
+                    Mirroring::Horizontal
+                } else {
+                    Mirroring::Vertical
+                }
+            }
+            0x9000 => {
+                self.mirroring = match v & 0b11 {
+                    0 => Mirroring::Vertical,
+                    1 => Mirroring::Horizontal,
+                    2 => Mirroring::SingleScreenA,
+                    _ => Mirroring::SingleScreenB,
+                }
+            }
+            0x9002 => self.program_swap = v.bit(1),
+            0xA000..=0xA003 => self.program_banks[1] = (v & 0x1F) as usize,
+            r @ 0xB000..=0xE003 => self.put_character_bank(r, v),
+            0xF000 if vrc4 => self.irq.put_latch_low(v),
+            0xF001 if vrc4 => self.irq.put_latch_high(v),
+            0xF002 if vrc4 => self.irq.put_control(v),
+            0xF003 if vrc4 => self.irq.acknowledge(),
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+impl Mapper for VRC {
+    fn character(&self, i: usize) -> Result<u8> {
+        self.character.get(self.character_offset(i))
+    }
+
+    fn put_character(&mut self, i: usize, v: u8) -> Result<()> {
+        self.character.put(self.character_offset(i), v)
+    }
+
+    fn sprite(&self, i: usize) -> Sprite {
+        self.character.sprite(self.character_offset(i))
+    }
+
+    fn mirroring(&self) -> Option<Mirroring> {
+        Some(self.mirroring)
+    }
+
+    fn clock(&mut self) {
+        if self.wiring.chip == Chip::VRC4 {
+            self.irq.clock();
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.irq.asserted()
+    }
+
+    fn battery(&self) -> Option<Vec<u8>> {
+        self.battery.then(|| self.ram.clone())
+    }
+
+    fn load_battery(&mut self, data: &[u8]) {
+        battery::restore(&mut self.ram, data);
+    }
+}
+
+#[cfg(test)]
+fn mock(mapper: u16, submapper: u8) -> VRC {
+    let program = (0..0x2000 * 16).map(|i| (i / 0x2000) as u8).collect::<Vec<u8>>();
+    let character = (0..0x0400 * 256).map(|i| (i / 0x0400) as u8).collect::<Vec<u8>>();
+    VRC::new(
+        Wiring::from(mapper, submapper).unwrap(),
+        &program,
+        Character::new(&character),
+        false,
+    )
+}
+
+#[test]
+fn it_wiring() {
+    // VRC4c selects registers by A6 / A7
+    let mut m = mock(21, 2);
+    m.put(0xB000, 0x05).unwrap();
+    m.put(0xB040, 0x01).unwrap();
+    m.put(0xB080, 0x07).unwrap();
+    assert_eq!(m.character(0x0000).unwrap(), 0x15);
+    assert_eq!(m.character(0x0400).unwrap(), 0x07);
+
+    // VRC2a drops the lowest bit of CHR banks
+    let mut m = mock(22, 0);
+    m.put(0xB000, 0x05).unwrap();
+    assert_eq!(m.character(0x0000).unwrap(), 0x02);
+}
+
+#[test]
+fn it_program_swap() {
+    let mut m = mock(25, 1);
+    m.put(0x8000, 3).unwrap();
+    assert_eq!(m.get(0x8000).unwrap(), 3);
+    assert_eq!(m.get(0xC000).unwrap(), 14);
+    // VRC4b: A1 is pin0, so 0x9002 is written by 0x9001
+    m.put(0x9001, 0b10).unwrap();
+    assert_eq!(m.get(0x8000).unwrap(), 14);
+    assert_eq!(m.get(0xC000).unwrap(), 3);
+    assert_eq!(m.get(0xE000).unwrap(), 15);
+}
+
+#[test]
+fn it_irq() {
+    let mut m = mock(23, 1);
+    m.put(0xF000, 0x0F).unwrap();
+    m.put(0xF001, 0x0F).unwrap();
+    m.put(0xF002, 0b110).unwrap();
+    m.clock();
+    assert!(m.irq());
+    m.put(0xF003, 0).unwrap();
+    assert!(!m.irq());
+}
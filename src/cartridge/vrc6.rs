@@ -0,0 +1,213 @@
+use super::bank;
+use super::battery;
+use super::character::Character;
+use super::mapper::{Mapper, Mirroring};
+use super::vrc6_audio::Vrc6Audio;
+use super::vrc_irq::VrcIrq;
+use crate::bits::Byte;
+use crate::memory::{RAM, ROM, WOM};
+use crate::result::{e, Result};
+use crate::sprite::Sprite;
+
+/// mapper 24, 26 (Konami VRC6)
+/// mapper 26 swaps A0 and A1.
+pub struct VRC6 {
+    swapped: bool,
+    program: Vec<u8>,
+    character: Character,
+    ram: Vec<u8>,
+    battery: bool,
+    ram_enabled: bool,
+    /// 16KB bank at 0x8000
+    program_bank16: usize,
+    /// 8KB bank at 0xC000
+    program_bank8: usize,
+    /// 1KB banks
+    character_banks: [usize; 8],
+    mirroring: Mirroring,
+    irq: VrcIrq,
+    audio: Vrc6Audio,
+}
+
+impl VRC6 {
+    pub fn new(swapped: bool, program: &[u8], character: Character, battery: bool) -> Self {
+        Self {
+            swapped,
+            program: program.to_vec(),
+            character,
+            ram: vec![0; 0x2000],
+            battery,
+            ram_enabled: false,
+            program_bank16: 0,
+            program_bank8: 0,
+            character_banks: [0; 8],
+            mirroring: Mirroring::Vertical,
+            irq: VrcIrq::default(),
+            audio: Vrc6Audio::default(),
+        }
+    }
+
+    /// CPU address to register address (0x8000, 0x8001, ..., 0xF003)
+    fn register(&self, i: usize) -> usize {
+        if self.swapped {
+            (i & 0xF000) | (i & 1) << 1 | (i & 2) >> 1
+        } else {
+            i & 0xF003
+        }
+    }
+
+    fn program_offset(&self, i: usize) -> usize {
+        let len = self.program.len();
+        match i {
+            _ if (0x8000..=0xBFFF).contains(&i) => {
+                bank::offset(len, 0x4000, self.program_bank16) + (i - 0x8000)
+            }
+            _ if (0xC000..=0xDFFF).contains(&i) => {
+                bank::offset(len, 0x2000, self.program_bank8) + (i - 0xC000)
+            }
+            _ => bank::last(len, 0x2000, 0) + (i - 0xE000),
+        }
+    }
+
+    fn character_offset(&self, i: usize) -> usize {
+        let n = self.character_banks[i / 0x0400];
+        bank::offset(self.character.len(), 0x0400, n) + (i & 0x03FF)
+    }
+
+    /// 0xB003
+    /// only the PPU banking mode 0 (eight 1KB CHR banks) is supported,
+    /// which is used by all the released games.
+    fn put_banking_mode(&mut self, v: u8) {
+        self.mirroring = match (v >> 2) & 0b11 {
+            0 => Mirroring::Vertical,
+            1 => Mirroring::Horizontal,
+            2 => Mirroring::SingleScreenA,
+            _ => Mirroring::SingleScreenB,
+        };
+        self.ram_enabled = v.bit(7);
+    }
+}
+
+impl RAM<usize> for VRC6 {}
+
+impl ROM<usize> for VRC6 {
+    type Output = u8;
+
+    fn get(&self, i: usize) -> Result<Self::Output> {
+        match i {
+            _ if (0x4020..=0x5FFF).contains(&i) => Ok(0),
+            _ if (0x6000..=0x7FFF).contains(&i) => {
+                if self.ram_enabled {
+                    self.ram.get(i - 0x6000)
+                } else {
+                    Ok(0)
+                }
+            }
+            _ if (0x8000..=0xFFFF).contains(&i) => self.program.get(self.program_offset(i)),
+            _ => Err(e::index_out_of_range(i)),
+        }
+    }
+}
+
+impl WOM<usize> for VRC6 {
+    type Input = u8;
+
+    fn put(&mut self, i: usize, v: Self::Input) -> Result<()> {
+        if (0x6000..=0x7FFF).contains(&i) {
+            if self.ram_enabled {
+                self.ram.put(i - 0x6000, v)?;
+            }
+            return Ok(());
+        }
+        if i < 0x8000 {
+            return Err(e::readonly(i));
+        }
+
+        match self.register(i) {
+            0x8000..=0x8003 => self.program_bank16 = (v & 0x0F) as usize,
+            r @ 0x9000..=0xB002 => self.audio.put(r, v),
+            0xB003 => self.put_banking_mode(v),
+            0xC000..=0xC003 => self.program_bank8 = (v & 0x1F) as usize,
+            r @ 0xD000..=0xE003 => {
+                let n = ((r >> 12) - 0xD) * 4 + (r & 0b11);
+                self.character_banks[n] = v as usize;
+            }
+            0xF000 => self.irq.put_latch(v),
+            0xF001 => self.irq.put_control(v),
+            0xF002 => self.irq.acknowledge(),
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+impl Mapper for VRC6 {
+    fn character(&self, i: usize) -> Result<u8> {
+        self.character.get(self.character_offset(i))
+    }
+
+    fn put_character(&mut self, i: usize, v: u8) -> Result<()> {
+        self.character.put(self.character_offset(i), v)
+    }
+
+    fn sprite(&self, i: usize) -> Sprite {
+        self.character.sprite(self.character_offset(i))
+    }
+
+    fn mirroring(&self) -> Option<Mirroring> {
+        Some(self.mirroring)
+    }
+
+    fn clock(&mut self) {
+        self.irq.clock();
+        self.audio.clock();
+    }
+
+    fn irq(&self) -> bool {
+        self.irq.asserted()
+    }
+
+    fn sample(&self) -> f32 {
+        self.audio.output()
+    }
+
+    fn battery(&self) -> Option<Vec<u8>> {
+        self.battery.then(|| self.ram.clone())
+    }
+
+    fn load_battery(&mut self, data: &[u8]) {
+        battery::restore(&mut self.ram, data);
+    }
+}
+
+#[cfg(test)]
+fn mock(swapped: bool) -> VRC6 {
+    let program = (0..0x2000 * 16).map(|i| (i / 0x2000) as u8).collect::<Vec<u8>>();
+    let character = (0..0x0400 * 256).map(|i| (i / 0x0400) as u8).collect::<Vec<u8>>();
+    VRC6::new(swapped, &program, Character::new(&character), false)
+}
+
+#[test]
+fn it_banks() {
+    let mut m = mock(false);
+    m.put(0x8000, 2).unwrap();
+    m.put(0xC000, 7).unwrap();
+    m.put(0xD001, 0x42).unwrap();
+    assert_eq!(m.get(0x8000).unwrap(), 4);
+    assert_eq!(m.get(0xA000).unwrap(), 5);
+    assert_eq!(m.get(0xC000).unwrap(), 7);
+    assert_eq!(m.get(0xE000).unwrap(), 15);
+    assert_eq!(m.character(0x0400).unwrap(), 0x42);
+}
+
+#[test]
+fn it_swapped_lines() {
+    let mut m = mock(true);
+    // 0xD002 on mapper 26 is 0xD001 on mapper 24
+    m.put(0xD002, 0x42).unwrap();
+    assert_eq!(m.character(0x0400).unwrap(), 0x42);
+    m.put(0xB001, 0b1000_0100).unwrap();
+    assert_eq!(m.mirroring(), Some(Mirroring::Vertical));
+    m.put(0xB003, 0b1000_0100).unwrap();
+    assert_eq!(m.mirroring(), Some(Mirroring::Horizontal));
+}
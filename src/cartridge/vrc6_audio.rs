@@ -0,0 +1,198 @@
+use crate::bits::Byte;
+
+/// period counter shared by the channels, clocked every CPU cycle
+#[derive(Debug, Default)]
+struct Divider {
+    period: u16,
+    counter: u16,
+    enabled: bool,
+}
+
+impl Divider {
+    fn put_low(&mut self, v: u8) {
+        self.period = (self.period & 0x0F00) | v as u16;
+    }
+
+    fn put_high(&mut self, v: u8) {
+        self.period = (self.period & 0x00FF) | ((v & 0x0F) as u16) << 8;
+        self.enabled = v.bit(7);
+    }
+
+    /// returns true when the counter reaches zero
+    fn clock(&mut self, shift: u16) -> bool {
+        if self.counter == 0 {
+            self.counter = self.period >> shift;
+            true
+        } else {
+            self.counter -= 1;
+            false
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Pulse {
+    divider: Divider,
+    volume: u8,
+    duty: u8,
+    /// ignore duty, output volume constantly
+    digitized: bool,
+    step: u8,
+}
+
+impl Pulse {
+    fn put_control(&mut self, v: u8) {
+        self.digitized = v.bit(7);
+        self.duty = (v >> 4) & 0b111;
+        self.volume = v & 0x0F;
+    }
+
+    fn put_high(&mut self, v: u8) {
+        self.divider.put_high(v);
+        if !self.divider.enabled {
+            self.step = 15;
+        }
+    }
+
+    fn clock(&mut self, shift: u16) {
+        if self.divider.enabled && self.divider.clock(shift) {
+            self.step = if self.step == 0 { 15 } else { self.step - 1 };
+        }
+    }
+
+    fn output(&self) -> u8 {
+        if self.divider.enabled && (self.digitized || self.step <= self.duty) {
+            self.volume
+        } else {
+            0
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Saw {
+    divider: Divider,
+    rate: u8,
+    accumulator: u8,
+    step: u8,
+}
+
+impl Saw {
+    fn put_high(&mut self, v: u8) {
+        self.divider.put_high(v);
+        if !self.divider.enabled {
+            self.accumulator = 0;
+            self.step = 0;
+        }
+    }
+
+    fn clock(&mut self, shift: u16) {
+        if !(self.divider.enabled && self.divider.clock(shift)) {
+            return;
+        }
+        // the accumulator is added on every second step and reset on the 14th step
+        self.step += 1;
+        if self.step == 14 {
+            self.step = 0;
+            self.accumulator = 0;
+        } else if self.step & 1 == 0 {
+            self.accumulator = self.accumulator.wrapping_add(self.rate);
+        }
+    }
+
+    fn output(&self) -> u8 {
+        if self.divider.enabled {
+            self.accumulator >> 3
+        } else {
+            0
+        }
+    }
+}
+
+/// two pulse channels and a sawtooth channel of VRC6
+#[derive(Debug, Default)]
+pub struct Vrc6Audio {
+    pulses: [Pulse; 2],
+    saw: Saw,
+    halt: bool,
+    /// 0, 4 or 8, divides all periods by 16 or 256
+    shift: u16,
+}
+
+impl Vrc6Audio {
+    /// register address is normalized to 0x9000～0xB002
+    pub fn put(&mut self, register: usize, v: u8) {
+        match register {
+            0x9000 => self.pulses[0].put_control(v),
+            0x9001 => self.pulses[0].divider.put_low(v),
+            0x9002 => self.pulses[0].put_high(v),
+            0x9003 => {
+                self.halt = v.bit(0);
+                self.shift = if v.bit(2) {
+                    8
+                } else if v.bit(1) {
+                    4
+                } else {
+                    0
+                };
+            }
+            0xA000 => self.pulses[1].put_control(v),
+            0xA001 => self.pulses[1].divider.put_low(v),
+            0xA002 => self.pulses[1].put_high(v),
+            0xB000 => self.saw.rate = v & 0x3F,
+            0xB001 => self.saw.divider.put_low(v),
+            0xB002 => self.saw.put_high(v),
+            _ => {}
+        }
+    }
+
+    pub fn clock(&mut self) {
+        if self.halt {
+            return;
+        }
+        for pulse in self.pulses.iter_mut() {
+            pulse.clock(self.shift);
+        }
+        self.saw.clock(self.shift);
+    }
+
+    /// 0.0～1.0
+    pub fn output(&self) -> f32 {
+        let v = self.pulses[0].output() + self.pulses[1].output() + self.saw.output();
+        // 15 + 15 + 31, roughly as loud as the APU pulse channels
+        v as f32 / 61.0 * 0.5
+    }
+}
+
+#[test]
+fn it_pulse() {
+    let mut audio = Vrc6Audio::default();
+    audio.put(0x9000, 0b0000_1111);
+    audio.put(0x9001, 0);
+    audio.put(0x9002, 0b1000_0000);
+    // duty 0 outputs volume only on the last step of 16
+    let outputs = (0..16)
+        .map(|_| {
+            audio.clock();
+            audio.output() > 0.0
+        })
+        .filter(|v| *v)
+        .count();
+    assert_eq!(outputs, 1);
+}
+
+#[test]
+fn it_saw() {
+    let mut audio = Vrc6Audio::default();
+    audio.put(0xB000, 0x08);
+    audio.put(0xB001, 0);
+    audio.put(0xB002, 0b1000_0000);
+    let mut max = 0;
+    for _ in 0..14 {
+        audio.clock();
+        max = std::cmp::max(max, audio.saw.output());
+    }
+    // 6 additions of 8 before reset
+    assert_eq!(max, 48 >> 3);
+    assert_eq!(audio.saw.output(), 0);
+}
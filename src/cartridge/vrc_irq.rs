@@ -0,0 +1,100 @@
+use crate::bits::Byte;
+
+/// IRQ counter shared by VRC4, VRC6 and VRC7.
+/// scanline mode emulates a scanline with a prescaler of 341 / 3 CPU cycles,
+/// cycle mode clocks the counter every CPU cycle.
+#[derive(Debug, Default)]
+pub struct VrcIrq {
+    latch: u8,
+    counter: u8,
+    prescaler: i16,
+    enable_after_ack: bool,
+    enabled: bool,
+    cycle_mode: bool,
+    asserted: bool,
+}
+
+impl VrcIrq {
+    pub fn put_latch(&mut self, v: u8) {
+        self.latch = v;
+    }
+
+    pub fn put_latch_low(&mut self, v: u8) {
+        self.latch = (self.latch & 0xF0) | (v & 0x0F);
+    }
+
+    pub fn put_latch_high(&mut self, v: u8) {
+        self.latch = (self.latch & 0x0F) | (v << 4);
+    }
+
+    pub fn put_control(&mut self, v: u8) {
+        self.enable_after_ack = v.bit(0);
+        self.enabled = v.bit(1);
+        self.cycle_mode = v.bit(2);
+        self.asserted = false;
+        if self.enabled {
+            self.counter = self.latch;
+            self.prescaler = 341;
+        }
+    }
+
+    pub fn acknowledge(&mut self) {
+        self.asserted = false;
+        self.enabled = self.enable_after_ack;
+    }
+
+    pub fn asserted(&self) -> bool {
+        self.asserted
+    }
+
+    pub fn clock(&mut self) {
+        if !self.enabled {
+            return;
+        }
+        if self.cycle_mode {
+            self.count();
+        } else {
+            self.prescaler -= 3;
+            if self.prescaler <= 0 {
+                self.prescaler += 341;
+                self.count();
+            }
+        }
+    }
+
+    fn count(&mut self) {
+        if self.counter == 0xFF {
+            self.counter = self.latch;
+            self.asserted = true;
+        } else {
+            self.counter += 1;
+        }
+    }
+}
+
+#[test]
+fn it_cycle_mode() {
+    let mut irq = VrcIrq::default();
+    irq.put_latch(0xFD);
+    irq.put_control(0b110);
+    irq.clock();
+    irq.clock();
+    assert!(!irq.asserted());
+    irq.clock();
+    assert!(irq.asserted());
+    irq.acknowledge();
+    assert!(!irq.asserted());
+}
+
+#[test]
+fn it_scanline_mode() {
+    let mut irq = VrcIrq::default();
+    irq.put_latch(0xFF);
+    irq.put_control(0b010);
+    for _ in 0..113 {
+        irq.clock();
+    }
+    assert!(!irq.asserted());
+    irq.clock();
+    assert!(irq.asserted());
+}
@@ -1,16 +1,18 @@
 use super::register::Register;
 use super::status_register::SFlag;
 use crate::bits::Byte;
-use crate::memory::{ROM, WOM};
+use crate::memory::{DMA, ROM, WOM};
 use crate::program::{IndexRegister, Opecode, Operand, CYCLES, ORDER_SET};
 use crate::result::Result;
 
 pub struct CPU<M>
 where
-    M: WOM<usize, Input = u8> + ROM<usize, Output = u8> + ROM<[usize; 2], Output = u16>,
+    M: WOM<usize, Input = u8> + ROM<usize, Output = u8> + ROM<[usize; 2], Output = u16> + DMA,
 {
     register: Register,
     memory: M,
+    /// cycles from the power on, DMA is aligned by them
+    cycles: usize,
 }
 
 impl<M> std::fmt::Display for CPU<M>
@@ -18,6 +20,7 @@ where
     M: WOM<usize, Input = u8>
         + ROM<usize, Output = u8>
         + ROM<[usize; 2], Output = u16>
+        + DMA
         + std::fmt::Display,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -53,10 +56,14 @@ pub enum R {
 
 impl<M> CPU<M>
 where
-    M: WOM<usize, Input = u8> + ROM<usize, Output = u8> + ROM<[usize; 2], Output = u16>,
+    M: WOM<usize, Input = u8> + ROM<usize, Output = u8> + ROM<[usize; 2], Output = u16> + DMA,
 {
     pub fn new(register: Register, memory: M) -> Self {
-        Self { register, memory }
+        Self {
+            register,
+            memory,
+            cycles: 0,
+        }
     }
 
     fn addr(&self, operand: Operand, pc: usize) -> Result<Value> {
@@ -146,6 +153,13 @@ where
             );
         }
         self.order(opecode, value)?;
+        // OAM DMA takes 513 cycles, and one more to wait for an even cycle
+        let cycle = if self.memory.take_dma() {
+            cycle + 513 + (self.cycles + cycle) % 2
+        } else {
+            cycle
+        };
+        self.cycles += cycle;
         Ok(cycle)
     }
 
@@ -440,6 +454,34 @@ where
         Ok(())
     }
 
+    /// interrupt request from devices, ignored while the I flag is on
+    /// cycles taken, 0 when the interrupt is disabled
+    pub fn irq(&mut self) -> Result<usize> {
+        if self.register.p.i() {
+            return Ok(0);
+        }
+        self.interrupt([0xFFFE, 0xFFFF])
+    }
+
+    /// cycles taken
+    pub fn nmi(&mut self) -> Result<usize> {
+        self.interrupt([0xFFFA, 0xFFFB])
+    }
+
+    /// pushes PC and P, then jumps by the vector in 7 cycles
+    fn interrupt(&mut self, vector: [usize; 2]) -> Result<usize> {
+        let (upper, lower) = binary::u16_to_u8(self.register.pc);
+        self.stack_push(upper)?;
+        self.stack_push(lower)?;
+        self.register.p.off(SFlag::B);
+        self.stack_push(u8::from(self.register.p))?;
+        self.register.p.on(SFlag::I);
+
+        self.register.pc = self.memory.get(vector)?;
+        self.cycles += 7;
+        Ok(7)
+    }
+
     fn brk(&mut self, _: Value) -> Result<()> {
         if !self.register.p.i() {
             self.register.p.on(SFlag::B);
@@ -453,8 +495,12 @@ where
         }
         Ok(())
     }
-    fn rti(&mut self, value: Value) -> Result<()> {
-        todo!();
+    fn rti(&mut self, _: Value) -> Result<()> {
+        self.register.p = self.stack_pop()?.into();
+        let lower = self.stack_pop()?;
+        let upper = self.stack_pop()?;
+        self.register.pc = u16::from_le_bytes([lower, upper]);
+        Ok(())
     }
 
     fn calc_cmp(&mut self, value: Value, flag: R) -> Result<()> {
@@ -688,3 +734,108 @@ where
         self.memory.get(self.register.sp as usize)
     }
 }
+
+#[test]
+fn it_dma() {
+    use super::MemoryMap;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    // LDA #$02 / STA $4014 / STA $4014
+    let mut program = vec![0xEA; 0x4000];
+    program[..8].copy_from_slice(&[0xA9, 0x02, 0x8D, 0x14, 0x40, 0x8D, 0x14, 0x40]);
+    program[0x3FFC..0x3FFE].copy_from_slice(&[0x00, 0x80]);
+    let mut data = crate::ines::INesHeader::new(0x4000, 0x2000)
+        .to_bytes()
+        .unwrap()
+        .to_vec();
+    data.extend_from_slice(&program);
+    data.resize(16 + 0x4000 + 0x2000, 0);
+    let ines = crate::ines::INes::parse(&data).unwrap();
+    let cartridge = crate::cartridge::load(&ines).unwrap();
+
+    let mut wram = vec![0; 0x800];
+    wram[0x2FF] = 0x55;
+    // OAMDATA takes the last byte of the page
+    let ppu = Rc::new(RefCell::new(vec![0; 8]));
+    let memory = MemoryMap::new(Rc::clone(&ppu), cartridge, wram, vec![0; 0x1F]);
+    let mut cpu = CPU::new(Register::default(), memory);
+    cpu.reset().unwrap();
+
+    assert_eq!(cpu.exec(false).unwrap(), 2);
+    // the write ends on an even cycle, then on an odd one
+    assert_eq!(cpu.exec(false).unwrap(), 4 + 513);
+    assert_eq!(ppu.borrow()[4], 0x55);
+    assert_eq!(cpu.exec(false).unwrap(), 4 + 514);
+}
+
+#[test]
+fn it_nmi() {
+    use super::MemoryMap;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    // SEC / NOP, and RTI at 0x8010 for NMI
+    let mut program = vec![0xEA; 0x4000];
+    program[0] = 0x38;
+    program[0x10] = 0x40;
+    program[0x3FFA..0x3FFC].copy_from_slice(&[0x10, 0x80]);
+    program[0x3FFC..0x3FFE].copy_from_slice(&[0x00, 0x80]);
+    let mut data = crate::ines::INesHeader::new(0x4000, 0x2000)
+        .to_bytes()
+        .unwrap()
+        .to_vec();
+    data.extend_from_slice(&program);
+    data.resize(16 + 0x4000 + 0x2000, 0);
+    let ines = crate::ines::INes::parse(&data).unwrap();
+    let cartridge = crate::cartridge::load(&ines).unwrap();
+
+    let ppu = Rc::new(RefCell::new(vec![0; 8]));
+    let memory = MemoryMap::new(ppu, cartridge, vec![0; 0x800], vec![0; 0x1F]);
+    let mut cpu = CPU::new(Register::default(), memory);
+    cpu.reset().unwrap();
+    cpu.exec(false).unwrap();
+    let (pc, p) = (cpu.register.pc, cpu.register.p);
+    assert_eq!(pc, 0x8001);
+    assert!(p.c());
+
+    assert_eq!(cpu.nmi().unwrap(), 7);
+    assert_eq!(cpu.register.pc, 0x8010);
+    assert!(cpu.register.p.i());
+    assert_eq!(cpu.exec(false).unwrap(), 6);
+    assert_eq!(cpu.register.pc, pc);
+    assert_eq!(cpu.register.p, p);
+}
+
+#[test]
+fn it_dma_unreadable() {
+    use super::MemoryMap;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    // LDA #$20 / STA $4014 / LDA #$40 / STA $4014
+    let mut program = vec![0xEA; 0x4000];
+    program[..10].copy_from_slice(&[0xA9, 0x20, 0x8D, 0x14, 0x40, 0xA9, 0x40, 0x8D, 0x14, 0x40]);
+    program[0x3FFC..0x3FFE].copy_from_slice(&[0x00, 0x80]);
+    let mut data = crate::ines::INesHeader::new(0x4000, 0x2000)
+        .to_bytes()
+        .unwrap()
+        .to_vec();
+    data.extend_from_slice(&program);
+    data.resize(16 + 0x4000 + 0x2000, 0);
+    let ines = crate::ines::INes::parse(&data).unwrap();
+    let cartridge = crate::cartridge::load(&ines).unwrap();
+
+    // the PPU registers past 0x2010 and 0x401E～0x401F can not be read
+    let ppu = Rc::new(RefCell::new(vec![0; 8]));
+    let memory = MemoryMap::new(Rc::clone(&ppu), cartridge, vec![0; 0x800], vec![0x33; 0x1E]);
+    let mut cpu = CPU::new(Register::default(), memory);
+    cpu.reset().unwrap();
+
+    cpu.exec(false).unwrap();
+    assert!(cpu.exec(false).unwrap() > 513);
+    assert_eq!(ppu.borrow()[4], 0);
+    cpu.exec(false).unwrap();
+    assert!(cpu.exec(false).unwrap() > 513);
+    assert_eq!(ppu.borrow()[4], 0);
+}
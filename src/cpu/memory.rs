@@ -1,60 +1,66 @@
-use crate::memory::{RAM, ROM, WOM};
+use crate::cartridge::Mapper;
+use crate::memory::{DMA, RAM, ROM, WOM};
 use crate::result::{e, Result};
 use std::cell::RefCell;
 use std::rc::Rc;
 
-pub struct MemoryMap<EXROM, EXRAM, PROM, APU, WRAM, PPU>
+pub struct MemoryMap<CART, APU, WRAM, PPU>
 where
-    EXROM: ROM<usize, Output = u8>,
-    EXRAM: RAM<usize, Output = u8, Input = u8>,
+    CART: Mapper + ?Sized,
     APU: RAM<usize, Output = u8, Input = u8>,
-    PROM: ROM<usize, Output = u8>,
     WRAM: RAM<usize, Output = u8, Input = u8>,
     PPU: RAM<usize, Output = u8, Input = u8>,
 {
-    exrom: EXROM,
-    exram: EXRAM,
     ppu_bus: Rc<RefCell<PPU>>,
     apu: APU,
-    program_bus: PROM,
+    /// 0x4020～0xFFFF, addressed by the CPU address
+    cartridge: Rc<RefCell<CART>>,
     wram_bus: WRAM,
+    /// OAM DMA ran by the write to 0x4014
+    dma: bool,
 }
 
-impl<EXRAM, EXROM, APU, PROM, WRAM, PPU> MemoryMap<EXROM, EXRAM, PROM, APU, WRAM, PPU>
+impl<CART, APU, WRAM, PPU> MemoryMap<CART, APU, WRAM, PPU>
 where
-    EXROM: ROM<usize, Output = u8>,
-    EXRAM: RAM<usize, Output = u8, Input = u8>,
+    CART: Mapper + ?Sized,
     APU: RAM<usize, Output = u8, Input = u8>,
-    PROM: ROM<usize, Output = u8>,
     WRAM: RAM<usize, Output = u8, Input = u8>,
     PPU: RAM<usize, Output = u8, Input = u8>,
 {
     pub fn new(
         ppu_bus: Rc<RefCell<PPU>>,
-        program_bus: PROM,
+        cartridge: Rc<RefCell<CART>>,
         wram_bus: WRAM,
         apu: APU,
-        exram: EXRAM,
-        exrom: EXROM,
     ) -> Self {
         MemoryMap {
             ppu_bus,
-            program_bus,
+            cartridge,
             apu,
             wram_bus,
-            exrom,
-            exram,
+            dma: false,
         }
     }
 }
 
-impl<EXROM, EXRAM, PROM, APU, WRAM, PPU> std::fmt::Display
-    for MemoryMap<EXROM, EXRAM, PROM, APU, WRAM, PPU>
+impl<CART, APU, WRAM, PPU> DMA
+    for MemoryMap<CART, APU, WRAM, PPU>
 where
-    EXROM: ROM<usize, Output = u8>,
-    EXRAM: RAM<usize, Output = u8, Input = u8>,
+    CART: Mapper + ?Sized,
+    APU: RAM<usize, Output = u8, Input = u8>,
+    WRAM: RAM<usize, Output = u8, Input = u8>,
+    PPU: RAM<usize, Output = u8, Input = u8>,
+{
+    fn take_dma(&mut self) -> bool {
+        std::mem::take(&mut self.dma)
+    }
+}
+
+impl<CART, APU, WRAM, PPU> std::fmt::Display
+    for MemoryMap<CART, APU, WRAM, PPU>
+where
+    CART: Mapper + ?Sized,
     APU: RAM<usize, Output = u8, Input = u8>,
-    PROM: ROM<usize, Output = u8>,
     WRAM: RAM<usize, Output = u8, Input = u8>,
     PPU: RAM<usize, Output = u8, Input = u8> + std::fmt::Display,
 {
@@ -63,25 +69,21 @@ where
     }
 }
 
-impl<EXROM, EXRAM, PROM, APU, WRAM, PPU> RAM<usize>
-    for MemoryMap<EXROM, EXRAM, PROM, APU, WRAM, PPU>
+impl<CART, APU, WRAM, PPU> RAM<usize>
+    for MemoryMap<CART, APU, WRAM, PPU>
 where
-    EXROM: ROM<usize, Output = u8>,
-    EXRAM: RAM<usize, Output = u8, Input = u8>,
+    CART: Mapper + ?Sized,
     APU: RAM<usize, Output = u8, Input = u8>,
-    PROM: ROM<usize, Output = u8>,
     WRAM: RAM<usize, Output = u8, Input = u8>,
     PPU: RAM<usize, Output = u8, Input = u8>,
 {
 }
 
-impl<EXROM, EXRAM, PROM, APU, WRAM, PPU> ROM<[usize; 2]>
-    for MemoryMap<EXROM, EXRAM, PROM, APU, WRAM, PPU>
+impl<CART, APU, WRAM, PPU> ROM<[usize; 2]>
+    for MemoryMap<CART, APU, WRAM, PPU>
 where
-    EXROM: ROM<usize, Output = u8>,
-    EXRAM: RAM<usize, Output = u8, Input = u8>,
+    CART: Mapper + ?Sized,
     APU: RAM<usize, Output = u8, Input = u8>,
-    PROM: ROM<usize, Output = u8>,
     WRAM: RAM<usize, Output = u8, Input = u8>,
     PPU: RAM<usize, Output = u8, Input = u8>,
 {
@@ -96,13 +98,11 @@ where
     }
 }
 
-impl<EXROM, EXRAM, PROM, APU, WRAM, PPU> ROM<usize>
-    for MemoryMap<EXROM, EXRAM, PROM, APU, WRAM, PPU>
+impl<CART, APU, WRAM, PPU> ROM<usize>
+    for MemoryMap<CART, APU, WRAM, PPU>
 where
-    EXROM: ROM<usize, Output = u8>,
-    EXRAM: RAM<usize, Output = u8, Input = u8>,
+    CART: Mapper + ?Sized,
     APU: RAM<usize, Output = u8, Input = u8>,
-    PROM: ROM<usize, Output = u8>,
     WRAM: RAM<usize, Output = u8, Input = u8>,
     PPU: RAM<usize, Output = u8, Input = u8>,
 {
@@ -121,23 +121,19 @@ where
             // apu: [u8; 0x401F - 0x4000],
             _ if (0x4000..=0x401D).contains(&i) => self.apu.get(i - 0x4000),
             // exrom: [u8; 0x5FFF - 0x4020],
-            _ if (0x4020..=0x5FFF).contains(&i) => self.exrom.get(i - 0x4020),
             // exram: [u8; 0x7FFF - 0x6000],
-            _ if (0x6000..=0x7FFF).contains(&i) => self.exram.get(i - 0x6000),
             // rom: [u8; 0xFFFF - 0x8000],
-            _ if (0x8000..=0xFFFF).contains(&i) => self.program_bus.get(i - 0x8000),
+            _ if (0x4020..=0xFFFF).contains(&i) => self.cartridge.borrow().get(i),
             _ => dbg!(Err(e::index_out_of_range(i))),
         }
     }
 }
 
-impl<EXROM, EXRAM, PROM, APU, WRAM, PPU> WOM<usize>
-    for MemoryMap<EXROM, EXRAM, PROM, APU, WRAM, PPU>
+impl<CART, APU, WRAM, PPU> WOM<usize>
+    for MemoryMap<CART, APU, WRAM, PPU>
 where
-    EXROM: ROM<usize, Output = u8>,
-    EXRAM: RAM<usize, Output = u8, Input = u8>,
+    CART: Mapper + ?Sized,
     APU: RAM<usize, Output = u8, Input = u8>,
-    PROM: ROM<usize, Output = u8>,
     WRAM: RAM<usize, Output = u8, Input = u8>,
     PPU: RAM<usize, Output = u8, Input = u8>,
 {
@@ -146,12 +142,24 @@ where
         match i {
             _ if (0x0000..=0x07FF).contains(&i) => self.wram_bus.put(i, v),
             _ if (0x0800..=0x1FFF).contains(&i) => Err(e::readonly(i)),
-            _ if (0x2000..=0x2007).contains(&i) => self.ppu_bus.borrow_mut().put(i - 0x2000, v),
+            _ if (0x2000..=0x2007).contains(&i) => {
+                self.cartridge.borrow_mut().ppu_register(i - 0x2000, v);
+                self.ppu_bus.borrow_mut().put(i - 0x2000, v)
+            }
             _ if (0x2008..=0x3FFF).contains(&i) => Err(e::unimplemented()),
+            // OAM DMA, the page 0xXX00 goes to OAMDATA
+            0x4014 => {
+                let page = (v as usize) << 8;
+                for addr in page..page + 0x100 {
+                    // write-only registers and unmapped addresses read as 0, the open bus is not emulated
+                    let v = self.get(addr).unwrap_or(0);
+                    self.ppu_bus.borrow_mut().put(4, v)?;
+                }
+                self.dma = true;
+                Ok(())
+            }
             _ if (0x4000..=0x401D).contains(&i) => self.apu.put(i - 0x4000, v),
-            _ if (0x4020..=0x5FFF).contains(&i) => Err(e::readonly(i)),
-            _ if (0x6000..=0x7FFF).contains(&i) => self.exram.put(i - 0x6000, v),
-            _ if (0x8000..=0xFFFF).contains(&i) => Err(e::readonly(i)),
+            _ if (0x4020..=0xFFFF).contains(&i) => self.cartridge.borrow_mut().put(i, v),
             _ => {
                 dbg!(Err(e::index_out_of_range(i)))
             }
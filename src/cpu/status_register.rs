@@ -55,16 +55,14 @@ impl From<u8> for StatusRegister {
 
 impl From<StatusRegister> for u8 {
     fn from(value: StatusRegister) -> Self {
-        let result = 0u8;
-        result.set(7, value.n());
-        result.set(6, value.v());
-        result.set(5, value.r());
-        result.set(4, value.b());
-        result.set(3, value.d());
-        result.set(2, value.i());
-        result.set(1, value.z());
-        result.set(0, value.c());
-        result
+        0u8.set(7, value.n())
+            .set(6, value.v())
+            .set(5, value.r())
+            .set(4, value.b())
+            .set(3, value.d())
+            .set(2, value.i())
+            .set(1, value.z())
+            .set(0, value.c())
     }
 }
 
@@ -0,0 +1,322 @@
+use super::hash::Hashes;
+use crate::cartridge::Mirroring;
+use crate::ines::{INesHeader, TvSystem};
+use crate::result::Result;
+use std::fs;
+use std::path::Path;
+
+/// used when no database is given, a missing file is not an error
+pub const DEFAULT_PATH: &str = "nes20db.xml";
+
+/// a game of the database, None fields are left as the header says
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Entry {
+    pub name: Option<String>,
+    pub crc32: Option<u32>,
+    pub md5: Option<[u8; 16]>,
+    pub sha1: Option<[u8; 20]>,
+    pub mapper: Option<u16>,
+    pub submapper: Option<u8>,
+    pub mirroring: Option<Mirroring>,
+    pub tv_system: Option<TvSystem>,
+    pub battery: Option<bool>,
+    /// default input device, the NES 2.0 expansion device number
+    pub expansion_device: Option<u8>,
+}
+
+impl Entry {
+    pub fn apply(&self, header: &mut INesHeader) {
+        if let Some(mapper) = self.mapper {
+            header.set_mapper(mapper, self.submapper.unwrap_or(0));
+        }
+        if let Some(mirroring) = self.mirroring {
+            header.set_mirroring(mirroring);
+        }
+        if let Some(tv_system) = self.tv_system {
+            header.set_tv_system(tv_system);
+        }
+        if let Some(battery) = self.battery {
+            header.set_battery(battery);
+        }
+        if let Some(expansion_device) = self.expansion_device {
+            header.set_expansion_device(expansion_device);
+        }
+    }
+
+    /// SHA-1 is compared first, CRC32 alone may collide
+    fn matches(&self, hashes: &Hashes) -> bool {
+        match (self.sha1, self.md5, self.crc32) {
+            (Some(sha1), _, _) => sha1 == hashes.sha1,
+            (None, Some(md5), _) => md5 == hashes.md5,
+            (None, None, Some(crc32)) => crc32 == hashes.crc32,
+            _ => false,
+        }
+    }
+}
+
+/// local game database, an XML export of the NES 2.0 database or a CSV file
+#[derive(Debug, Default)]
+pub struct Database {
+    entries: Vec<Entry>,
+}
+
+impl Database {
+    /// CSV when the extension is .csv, XML otherwise
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .map_err(|err| anyhow::anyhow!("{}: {}", path.display(), err))?;
+        let csv = path
+            .extension()
+            .is_some_and(|v| v.eq_ignore_ascii_case("csv"));
+        let database = if csv {
+            Self::parse_csv(&text)
+        } else {
+            Self::parse_xml(&text)
+        };
+        database.map_err(|err| anyhow::anyhow!("{}: {}", path.display(), err))
+    }
+
+    /// the given file, or DEFAULT_PATH if it exists
+    pub fn open(path: Option<&Path>) -> Result<Option<Self>> {
+        match path {
+            Some(path) => Self::load(path).map(Some),
+            None if Path::new(DEFAULT_PATH).exists() => {
+                Self::load(Path::new(DEFAULT_PATH)).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn find(&self, hashes: &Hashes) -> Option<&Entry> {
+        self.entries.iter().find(|entry| entry.matches(hashes))
+    }
+
+    /// <game> elements of nes20db.xml, the name is taken from the comment in the element
+    /// ```xml
+    /// <game>
+    ///   <!-- Super Mario Bros. (World).nes -->
+    ///   <rom size="40960" crc32="3337EC46" sha1="EA343F4E445A9050D4B4FBAC2C77D0693B1D0922"/>
+    ///   <pcb mapper="0" submapper="0" mirroring="V" battery="0"/>
+    ///   <console type="0" region="0"/>
+    ///   <expansion type="1"/>
+    /// </game>
+    /// ```
+    pub fn parse_xml(text: &str) -> Result<Self> {
+        let document =
+            roxmltree::Document::parse(text).map_err(|err| anyhow::anyhow!("{}", err))?;
+        let mut entries = vec![];
+        for game in document.descendants().filter(|n| n.has_tag_name("game")) {
+            let mut entry = Entry::default();
+            for node in game.children() {
+                if node.is_comment() && entry.name.is_none() {
+                    entry.name = node.text().map(|v| v.trim().to_string());
+                }
+                let attribute = |name| node.attribute(name);
+                match node.tag_name().name() {
+                    "rom" => {
+                        entry.crc32 = parse_crc32(attribute("crc32"))?;
+                        entry.md5 = parse_hex(attribute("md5"))?;
+                        entry.sha1 = parse_hex(attribute("sha1"))?;
+                    }
+                    "pcb" => {
+                        entry.mapper = parse(attribute("mapper"))?;
+                        entry.submapper = parse(attribute("submapper"))?;
+                        entry.mirroring = parse_mirroring(attribute("mirroring"));
+                        entry.battery = parse_bool(attribute("battery"))?;
+                    }
+                    "console" => entry.tv_system = parse_region(attribute("region"))?,
+                    "expansion" => entry.expansion_device = parse(attribute("type"))?,
+                    _ => {}
+                }
+            }
+            entries.push(entry);
+        }
+        Ok(Self { entries })
+    }
+
+    /// a header row names the columns, in any order:
+    /// name, crc32, md5, sha1, mapper, submapper, mirroring, region, battery, expansion.
+    /// values are written as in nes20db.xml and empty cells are ignored.
+    pub fn parse_csv(text: &str) -> Result<Self> {
+        let mut reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_reader(text.as_bytes());
+        let headers = reader.headers()?.clone();
+        let column = |name: &str| headers.iter().position(|v| v.eq_ignore_ascii_case(name));
+        let columns = [
+            "name",
+            "crc32",
+            "md5",
+            "sha1",
+            "mapper",
+            "submapper",
+            "mirroring",
+            "region",
+            "battery",
+            "expansion",
+        ]
+        .map(column);
+
+        let mut entries = vec![];
+        for (row, record) in reader.records().enumerate() {
+            let record = record?;
+            let [name, crc32, md5, sha1, mapper, submapper, mirroring, region, battery, expansion] =
+                columns.map(|i| i.and_then(|i| record.get(i)));
+            let entry = (|| {
+                Ok(Entry {
+                    name: name.filter(|v| !v.is_empty()).map(|v| v.to_string()),
+                    crc32: parse_crc32(crc32)?,
+                    md5: parse_hex(md5)?,
+                    sha1: parse_hex(sha1)?,
+                    mapper: parse(mapper)?,
+                    submapper: parse(submapper)?,
+                    mirroring: parse_mirroring(mirroring),
+                    tv_system: parse_region(region)?,
+                    battery: parse_bool(battery)?,
+                    expansion_device: parse(expansion)?,
+                })
+            })()
+            .map_err(|err: anyhow::Error| anyhow::anyhow!("row {}: {}", row + 2, err))?;
+            entries.push(entry);
+        }
+        Ok(Self { entries })
+    }
+}
+
+fn parse<T: std::str::FromStr>(v: Option<&str>) -> Result<Option<T>> {
+    match v {
+        None | Some("") => Ok(None),
+        Some(v) => v
+            .parse()
+            .map(Some)
+            .map_err(|_| anyhow::anyhow!("bad number {:?}", v)),
+    }
+}
+
+fn parse_crc32(v: Option<&str>) -> Result<Option<u32>> {
+    Ok(parse_hex::<4>(v)?.map(u32::from_be_bytes))
+}
+
+fn parse_hex<const N: usize>(v: Option<&str>) -> Result<Option<[u8; N]>> {
+    let v = match v {
+        None | Some("") => return Ok(None),
+        Some(v) => v,
+    };
+    let error = || anyhow::anyhow!("bad hash {:?}, {} hex digits are expected", v, N * 2);
+    if v.len() != N * 2 || !v.is_ascii() {
+        return Err(error());
+    }
+    let mut hash = [0; N];
+    for (i, byte) in hash.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&v[i * 2..i * 2 + 2], 16).map_err(|_| error())?;
+    }
+    Ok(Some(hash))
+}
+
+/// mapper-controlled mirroring has no fixed value and is left as the header says
+fn parse_mirroring(v: Option<&str>) -> Option<Mirroring> {
+    match v {
+        Some("H") => Some(Mirroring::Horizontal),
+        Some("V") => Some(Mirroring::Vertical),
+        Some("4") => Some(Mirroring::FourScreen),
+        _ => None,
+    }
+}
+
+/// 0: NTSC, 1: PAL, 2: multiple-region, 3: Dendy
+fn parse_region(v: Option<&str>) -> Result<Option<TvSystem>> {
+    Ok(match parse::<u8>(v)? {
+        Some(0) => Some(TvSystem::NTSC),
+        Some(1) => Some(TvSystem::PAL),
+        Some(2) => Some(TvSystem::Multiple),
+        Some(3) => Some(TvSystem::Dendy),
+        Some(n) => return Err(anyhow::anyhow!("bad region {}", n)),
+        None => None,
+    })
+}
+
+fn parse_bool(v: Option<&str>) -> Result<Option<bool>> {
+    Ok(parse::<u8>(v)?.map(|v| v != 0))
+}
+
+#[cfg(test)]
+fn hashes() -> Hashes {
+    Hashes::new(b"ab", b"c")
+}
+
+#[test]
+fn it_parse_xml() {
+    let db = Database::parse_xml(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+        <nes20db>
+          <game>
+            <!-- Other.nes -->
+            <rom size="3" crc32="00000000" sha1="0000000000000000000000000000000000000000"/>
+            <pcb mapper="1" submapper="0" mirroring="H" battery="1"/>
+          </game>
+          <game>
+            <!-- Test.nes -->
+            <prgrom size="2" crc32="9E83486D"/>
+            <rom size="3" crc32="352441C2" sha1="A9993E364706816ABA3E25717850C26C9CD0D89D"/>
+            <pcb mapper="4" submapper="1" mirroring="V" battery="1"/>
+            <console type="0" region="1"/>
+            <expansion type="1"/>
+          </game>
+        </nes20db>"#,
+    )
+    .unwrap();
+    assert_eq!(db.len(), 2);
+    let entry = db.find(&hashes()).unwrap();
+    assert_eq!(entry.name.as_deref(), Some("Test.nes"));
+    assert_eq!(entry.mapper, Some(4));
+    assert_eq!(entry.submapper, Some(1));
+    assert_eq!(entry.mirroring, Some(Mirroring::Vertical));
+    assert_eq!(entry.tv_system, Some(TvSystem::PAL));
+    assert_eq!(entry.battery, Some(true));
+    assert_eq!(entry.expansion_device, Some(1));
+}
+
+#[test]
+fn it_parse_csv() {
+    let db = Database::parse_csv(
+        "name,crc32,md5,mapper,mirroring,region\n\
+         \"Game, The\",352441C2,,2,H,0\n\
+         Other,,900150983CD24FB0D6963F7D28E17F72,3,,\n",
+    )
+    .unwrap();
+    // the first row matches only CRC32
+    let entry = db.find(&hashes()).unwrap();
+    assert_eq!(entry.name.as_deref(), Some("Game, The"));
+    assert_eq!(entry.mapper, Some(2));
+    assert_eq!(entry.tv_system, Some(TvSystem::NTSC));
+
+    let error = Database::parse_csv("crc32\nXYZ\n").unwrap_err().to_string();
+    assert!(error.contains("row 2"), "{}", error);
+}
+
+#[test]
+fn it_apply() {
+    let mut header = INesHeader::new(0x8000, 0x2000);
+    let entry = Entry {
+        mapper: Some(4),
+        submapper: Some(1),
+        mirroring: Some(Mirroring::FourScreen),
+        tv_system: Some(TvSystem::Dendy),
+        battery: Some(true),
+        ..Default::default()
+    };
+    entry.apply(&mut header);
+    assert_eq!(header.mapper(), 4);
+    assert_eq!(header.submapper(), 1);
+    assert_eq!(header.mirroring(), Mirroring::FourScreen);
+    assert_eq!(header.tv_system(), TvSystem::Dendy);
+    assert!(header.battery());
+}
@@ -0,0 +1,49 @@
+use md5::Md5;
+use sha1::{Digest, Sha1};
+
+/// hashes of PRG ROM followed by CHR ROM, the header and the trainer are not included
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hashes {
+    pub crc32: u32,
+    pub md5: [u8; 16],
+    pub sha1: [u8; 20],
+}
+
+impl Hashes {
+    pub fn new(program: &[u8], character: &[u8]) -> Self {
+        let mut crc32 = crc32fast::Hasher::new();
+        let mut md5 = Md5::new();
+        let mut sha1 = Sha1::new();
+        for data in [program, character] {
+            crc32.update(data);
+            md5.update(data);
+            sha1.update(data);
+        }
+        Self {
+            crc32: crc32.finalize(),
+            md5: md5.finalize().into(),
+            sha1: sha1.finalize().into(),
+        }
+    }
+}
+
+/// upper case hex, as written in the databases
+pub fn hex(data: &[u8]) -> String {
+    data.iter().map(|v| format!("{:02X}", v)).collect()
+}
+
+impl std::fmt::Display for Hashes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "CRC32     : {:08X}", self.crc32)?;
+        writeln!(f, "MD5       : {}", hex(&self.md5))?;
+        write!(f, "SHA-1     : {}", hex(&self.sha1))
+    }
+}
+
+#[test]
+fn it_hashes() {
+    let h = Hashes::new(b"ab", b"c");
+    assert_eq!(h.crc32, 0x352441C2);
+    assert_eq!(hex(&h.md5), "900150983CD24FB0D6963F7D28E17F72");
+    assert_eq!(hex(&h.sha1), "A9993E364706816ABA3E25717850C26C9CD0D89D");
+}
@@ -0,0 +1,5 @@
+mod db;
+mod hash;
+
+pub use db::{Database, Entry, DEFAULT_PATH};
+pub use hash::{hex, Hashes};
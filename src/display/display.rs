@@ -1,5 +1,4 @@
 use crate::ppu;
-use crate::sprite::Sprite;
 use macroquad::prelude::{color_u8, Color, Image, BLACK};
 
 pub const W: usize = 256;
@@ -17,37 +16,8 @@ impl Default for Display {
 }
 
 impl Display {
-    pub fn put_image(&mut self, x: usize, y: usize, sprite: &Sprite, palette: &ppu::Palette) {
-        if !((0..32).contains(&x) && (0..30).contains(&y)) {
-            panic!("out of range, {}, {}", x, y);
-        }
-
-        let sprite_bits = sprite.bits();
-        for iy in 0..8 {
-            for ix in 0..8 {
-                let ty = y * 8 + iy;
-                let tx = x * 8 + ix;
-
-                let c = match sprite_bits[iy][ix].clone() {
-                    0 => palette.0,
-                    1 => palette.1,
-                    2 => palette.2,
-                    3 => palette.3,
-                    _ => unreachable!(),
-                };
-
-                self.image
-                    .set_pixel(tx as u32, ty as u32, color_u8!(c.r, c.g, c.b, 255.0));
-            }
-        }
-    }
-
-    pub fn put_plane(&mut self, x: usize, y: usize, color: &ppu::Color) {
-        self.put_image(
-            x,
-            y,
-            &Sprite::default(),
-            &ppu::Palette(color.clone(), color.clone(), color.clone(), color.clone()),
-        )
+    pub fn put_pixel(&mut self, x: usize, y: usize, c: &ppu::Color) {
+        self.image
+            .set_pixel(x as u32, y as u32, color_u8!(c.r, c.g, c.b, 255.0));
     }
 }
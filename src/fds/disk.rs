@@ -0,0 +1,216 @@
+use crate::result::Result;
+
+/// b"FDS\x1A"
+const MAGIC: [u8; 4] = [0x46, 0x44, 0x53, 0x1A];
+/// fwNES header of .fds
+const HEADER_LENGTH: usize = 16;
+/// a side of .fds, blocks without gaps and CRCs
+pub const SIDE_LENGTH: usize = 65500;
+/// a side of QD, a CRC follows each block
+const QD_SIDE_LENGTH: usize = 65536;
+/// block 1 starts with the block code and "*NINTENDO-HVC*"
+const VERIFICATION: &[u8] = b"\x01*NINTENDO-HVC*";
+/// 28300 bits of gap before the first block
+const LEADING_GAP: usize = 28300 / 8;
+/// 976 bits of gap after each block
+const BLOCK_GAP: usize = 976 / 8;
+/// the drive reads this many bytes at least before the head reaches the end
+const STREAM_LENGTH: usize = 75000;
+
+/// the BIOS is looked up next to the disk image by default
+pub const BIOS_FILE_NAME: &str = "disksys.rom";
+
+/// FDS CRC of a block, the start mark 0x80 included
+pub fn crc(data: &[u8]) -> u16 {
+    data.iter()
+        .chain(&[0, 0])
+        .fold(0, |crc, &v| update_crc(crc, v))
+}
+
+/// shifts a byte into the CRC, the drive does it as it reads or writes
+pub fn update_crc(mut crc: u16, v: u8) -> u16 {
+    for bit in 0..8 {
+        let carry = crc & 1 == 1;
+        crc >>= 1;
+        if carry {
+            crc ^= 0x8408;
+        }
+        if v >> bit & 1 == 1 {
+            crc ^= 0x8000;
+        }
+    }
+    crc
+}
+
+/// Famicom Disk System image (.fds with or without the fwNES header, or QD)
+#[derive(Debug)]
+pub struct DiskImage {
+    /// bytes of each side as the drive head reads them: gaps, start marks, blocks and CRCs
+    sides: Vec<Vec<u8>>,
+}
+
+impl DiskImage {
+    pub fn is_fds(data: &[u8]) -> bool {
+        data.starts_with(&MAGIC) || data.starts_with(VERIFICATION)
+    }
+
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let (body, side_length, crc_length) = if data.starts_with(&MAGIC) {
+            (data.get(HEADER_LENGTH..).unwrap_or(&[]), SIDE_LENGTH, 0)
+        } else if data.starts_with(VERIFICATION) && data.len().is_multiple_of(QD_SIDE_LENGTH) {
+            (data, QD_SIDE_LENGTH, 2)
+        } else if data.starts_with(VERIFICATION) {
+            (data, SIDE_LENGTH, 0)
+        } else {
+            return Err(anyhow::anyhow!("not FDS format, bad magic"));
+        };
+        // the side count of the header is often wrong, the size tells it
+        if body.is_empty() || !body.len().is_multiple_of(side_length) {
+            return Err(anyhow::anyhow!(
+                "not FDS format, {} bytes are not sides of {} bytes",
+                body.len(),
+                side_length
+            ));
+        }
+        let sides = body
+            .chunks(side_length)
+            .enumerate()
+            .map(|(n, side)| {
+                stream(side, crc_length).map_err(|err| anyhow::anyhow!("side {}: {}", n, err))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { sides })
+    }
+
+    pub fn sides(&self) -> &[Vec<u8>] {
+        &self.sides
+    }
+}
+
+/// blocks of a side with gaps and CRCs, as the drive reads them
+pub fn stream(side: &[u8], crc_length: usize) -> Result<Vec<u8>> {
+    if !side.starts_with(VERIFICATION) {
+        return Err(anyhow::anyhow!("no disk info block"));
+    }
+    let mut stream = vec![0; LEADING_GAP];
+    let mut offset = 0;
+    let mut file_size = 0;
+    while offset < side.len() {
+        let length = match side[offset] {
+            1 => 56,
+            2 => 2,
+            3 => 16,
+            4 => 1 + file_size,
+            // the rest of the side is blank
+            _ => break,
+        };
+        let block = side.get(offset..offset + length).ok_or_else(|| {
+            anyhow::anyhow!("truncated block {} at offset {}", side[offset], offset)
+        })?;
+        if block[0] == 3 {
+            file_size = u16::from_le_bytes([block[13], block[14]]) as usize;
+        }
+        let start = stream.len();
+        stream.push(0x80);
+        stream.extend_from_slice(block);
+        let crc = crc(&stream[start..]);
+        stream.extend_from_slice(&crc.to_le_bytes());
+        stream.extend_from_slice(&[0; BLOCK_GAP]);
+        offset += length + crc_length;
+    }
+    stream.resize(stream.len().max(STREAM_LENGTH), 0);
+    Ok(stream)
+}
+
+/// blocks of a side in the .fds layout, the gaps and CRCs of the stream dropped
+pub fn side(stream: &[u8]) -> Vec<u8> {
+    let mut side = Vec::with_capacity(SIDE_LENGTH);
+    let mut offset = 0;
+    let mut file_size = 0;
+    // each block follows the start mark 0x80 at the end of a gap
+    while let Some(mark) = stream
+        .get(offset..)
+        .and_then(|rest| rest.iter().position(|&v| v == 0x80))
+    {
+        offset += mark + 1;
+        let length = match stream[offset..].first() {
+            Some(1) => 56,
+            Some(2) => 2,
+            Some(3) => 16,
+            Some(4) => 1 + file_size,
+            _ => break,
+        };
+        let Some(block) = stream.get(offset..offset + length) else {
+            break;
+        };
+        if block[0] == 3 {
+            file_size = u16::from_le_bytes([block[13], block[14]]) as usize;
+        }
+        side.extend_from_slice(block);
+        offset += length + 2;
+    }
+    side.resize(SIDE_LENGTH, 0);
+    side
+}
+
+impl std::fmt::Display for DiskImage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "format    : FDS\nsides     : {}", self.sides.len())
+    }
+}
+
+#[cfg(test)]
+pub fn mock_side() -> Vec<u8> {
+    let mut side = VERIFICATION.to_vec();
+    side.resize(56, 0);
+    side.extend_from_slice(&[2, 1]);
+    side.extend_from_slice(&[3, 0, 0, b'F', b'I', b'L', b'E', b' ', b' ', b' ', b' ']);
+    side.extend_from_slice(&[0x00, 0x60, 3, 0, 0]);
+    side.extend_from_slice(&[4, 0xAA, 0xBB, 0xCC]);
+    side.resize(SIDE_LENGTH, 0);
+    side
+}
+
+#[test]
+fn it_parse() {
+    let mut data = MAGIC.to_vec();
+    data.push(2);
+    data.resize(HEADER_LENGTH, 0);
+    data.extend(mock_side());
+    data.extend(mock_side());
+    let disk = DiskImage::parse(&data).unwrap();
+    assert_eq!(disk.sides().len(), 2);
+
+    let side = &disk.sides()[0];
+    assert!(side[..LEADING_GAP].iter().all(|&v| v == 0));
+    assert_eq!(side[LEADING_GAP], 0x80);
+    assert_eq!(&side[LEADING_GAP + 1..LEADING_GAP + 16], VERIFICATION);
+    // the file data block is the 4th
+    let file =
+        LEADING_GAP + (1 + 56 + 2 + BLOCK_GAP) + (1 + 2 + 2 + BLOCK_GAP) + (1 + 16 + 2 + BLOCK_GAP);
+    assert_eq!(side[file..file + 5], [0x80, 4, 0xAA, 0xBB, 0xCC]);
+    assert_eq!(side.len(), STREAM_LENGTH);
+
+    // headerless images are told by the size
+    let disk = DiskImage::parse(&mock_side()).unwrap();
+    assert_eq!(disk.sides().len(), 1);
+    assert!(DiskImage::parse(&mock_side()[..1000]).is_err());
+}
+
+#[test]
+fn it_crc() {
+    // a block followed by its CRC gives 0
+    let mut block = vec![0x80, 2, 1];
+    block.extend_from_slice(&crc(&block).to_le_bytes());
+    assert_eq!(crc(&block), 0);
+}
+
+#[test]
+fn it_side() {
+    let stream = stream(&mock_side(), 0).unwrap();
+    assert_eq!(side(&stream), mock_side());
+    // bytes written into a gap are not blocks
+    let mut written = stream.clone();
+    written[..3].copy_from_slice(&[0x5A; 3]);
+    assert_eq!(side(&written), mock_side());
+}
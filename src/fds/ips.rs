@@ -0,0 +1,93 @@
+use crate::result::Result;
+
+const HEADER: &[u8] = b"PATCH";
+const FOOTER: &[u8] = b"EOF";
+/// offsets are 24 bit, and this one reads as the footer
+const FOOTER_OFFSET: usize = 0x454F46;
+const MAX_RECORD_LENGTH: usize = 0xFFFF;
+
+/// IPS patch which turns `original` into `modified`, both of the same length
+pub fn diff(original: &[u8], modified: &[u8]) -> Vec<u8> {
+    let mut patch = HEADER.to_vec();
+    let mut i = 0;
+    while i < modified.len() {
+        if original.get(i) == Some(&modified[i]) {
+            i += 1;
+            continue;
+        }
+        let start = if i == FOOTER_OFFSET { i - 1 } else { i };
+        let mut end = i;
+        while end < modified.len()
+            && end - start < MAX_RECORD_LENGTH
+            && original.get(end) != Some(&modified[end])
+        {
+            end += 1;
+        }
+        patch.extend_from_slice(&(start as u32).to_be_bytes()[1..]);
+        patch.extend_from_slice(&((end - start) as u16).to_be_bytes());
+        patch.extend_from_slice(&modified[start..end]);
+        i = end;
+    }
+    patch.extend_from_slice(FOOTER);
+    patch
+}
+
+/// apply an IPS patch, records out of the data are an error
+pub fn apply(data: &mut [u8], patch: &[u8]) -> Result<()> {
+    if !patch.starts_with(HEADER) {
+        return Err(anyhow::anyhow!("not IPS format, bad magic"));
+    }
+    let truncated = || anyhow::anyhow!("truncated IPS patch");
+    let mut offset = HEADER.len();
+    loop {
+        let record = patch.get(offset..offset + 3).ok_or_else(truncated)?;
+        if record == FOOTER {
+            return Ok(());
+        }
+        let at = u32::from_be_bytes([0, record[0], record[1], record[2]]) as usize;
+        let size = patch.get(offset + 3..offset + 5).ok_or_else(truncated)?;
+        let size = u16::from_be_bytes([size[0], size[1]]) as usize;
+        offset += 5;
+        // size 0 is a run of a byte
+        let (length, bytes) = if size == 0 {
+            let run = patch.get(offset..offset + 3).ok_or_else(truncated)?;
+            offset += 3;
+            (u16::from_be_bytes([run[0], run[1]]) as usize, &run[2..3])
+        } else {
+            let bytes = patch.get(offset..offset + size).ok_or_else(truncated)?;
+            offset += size;
+            (size, bytes)
+        };
+        let len = data.len();
+        let target = data.get_mut(at..at + length).ok_or_else(|| {
+            anyhow::anyhow!("IPS record at {} is out of the data of {} bytes", at, len)
+        })?;
+        for (i, v) in target.iter_mut().enumerate() {
+            *v = bytes[i % bytes.len()];
+        }
+    }
+}
+
+#[test]
+fn it_diff_and_apply() {
+    let original = vec![0; 0x454F50];
+    let mut modified = original.clone();
+    modified[3] = 1;
+    modified[4] = 2;
+    modified[FOOTER_OFFSET] = 3;
+    let patch = diff(&original, &modified);
+    assert_eq!(&patch[5..12], &[0, 0, 3, 0, 2, 1, 2]);
+
+    let mut data = original.clone();
+    apply(&mut data, &patch).unwrap();
+    assert!(data == modified);
+    assert_eq!(diff(&original, &original), b"PATCHEOF");
+    assert!(apply(&mut data, b"PATCH\x00\x00").is_err());
+}
+
+#[test]
+fn it_apply_run() {
+    let mut data = vec![0; 8];
+    apply(&mut data, b"PATCH\x00\x00\x02\x00\x00\x00\x03\x07EOF").unwrap();
+    assert_eq!(data, [0, 0, 7, 7, 7, 0, 0, 0]);
+}
@@ -0,0 +1,7 @@
+mod disk;
+mod ips;
+
+#[cfg(test)]
+pub use disk::mock_side;
+pub use disk::{crc, side, stream, update_crc, DiskImage, BIOS_FILE_NAME, SIDE_LENGTH};
+pub use ips::{apply, diff};
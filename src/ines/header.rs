@@ -1,23 +1,68 @@
+use crate::cartridge::Mirroring;
+
+/// CPU/PPU timing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TvSystem {
+    NTSC,
+    PAL,
+    /// runs on both NTSC and PAL consoles
+    Multiple,
+    Dendy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Console {
+    NES,
+    /// Nintendo VS System with the PPU type and the hardware type
+    VsSystem {
+        ppu: u8,
+        hardware: u8,
+    },
+    PlayChoice10,
+    /// NES 2.0 extended console type
+    Extended(u8),
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct INesHeader {
     /// Constant $4E $45 $53 $1A (ASCII "NES" followed by MS-DOS end-of-file)
     magic: [u8; 4],
-    /// Size of PRG ROM in 16 KB units
-    program_rom_unit_count: u8,
-    /// Size of CHR ROM in 8 KB units (value 0 means the board uses CHR RAM)
-    character_rom_unit_count: u8,
-    /// Flags 6 – Mapper, mirroring, battery, trainer
-    flag6: u8,
-    /// Flags 7 – Mapper, VS/Playchoice, NES 2.0
-    flag7: u8,
-    /// Flags 8 – PRG-RAM size (rarely used extension)
-    flag8: u8,
-    /// Flags 9 – TV system (rarely used extension)
-    flag9: u8,
-    /// Flags 10 – TV system, PRG-RAM presence (unofficial, rarely used extension)
-    flag10: u8,
-    /// Unused padding (should be filled with zero, but some rippers put their name across bytes 7-15)
-    padding: [u8; 5],
+    /// Flags 7 bit 2-3 == 0b10
+    nes2: bool,
+    /// PRG ROM in bytes (16 KB units in iNES, exponent-multiplier notation in NES 2.0)
+    program_rom_size: usize,
+    /// CHR ROM in bytes (value 0 means the board uses CHR RAM)
+    character_rom_size: usize,
+    /// Flags 6 upper nibble, Flags 7 upper nibble and NES 2.0 byte 8 lower nibble
+    mapper: u16,
+    /// NES 2.0 byte 8 upper nibble
+    submapper: u8,
+    /// Flags 6 bit 0, 0: horizontal (vertical arrangement), 1: vertical (horizontal arrangement)
+    mirroring: Mirroring,
+    /// Flags 6 bit 1, battery-backed PRG RAM or other persistent memory
+    battery: bool,
+    /// Flags 6 bit 2, 512-byte trainer at $7000-$71FF
+    trainer: bool,
+    /// Flags 6 bit 3, ignore mirroring control and provide four-screen VRAM
+    four_screen: bool,
+    /// Flags 7 bit 0-1 (NES 2.0 byte 13 for VS System and extended console types)
+    console: Console,
+    /// volatile PRG RAM in bytes (Flags 8 in iNES, byte 10 in NES 2.0)
+    program_ram_size: usize,
+    /// non-volatile PRG RAM in bytes (NES 2.0 byte 10)
+    program_nvram_size: usize,
+    /// volatile CHR RAM in bytes (NES 2.0 byte 11)
+    character_ram_size: usize,
+    /// non-volatile CHR RAM in bytes (NES 2.0 byte 11)
+    character_nvram_size: usize,
+    /// Flags 9 bit 0 in iNES, byte 12 in NES 2.0
+    tv_system: TvSystem,
+    /// NES 2.0 byte 14, number of ROMs after CHR ROM
+    misc_roms: u8,
+    /// NES 2.0 byte 15
+    expansion_device: u8,
+    /// iNES bytes 12-15 are not zero, flags 7-9 were ignored
+    junk: bool,
 }
 
 /// b"NES\0"
@@ -25,32 +70,191 @@ const MAGIC: [u8; 4] = [0x4E, 0x45, 0x53, 0x1A];
 const MAGIC_SUPER_MARIO: [u8; 4] = [0x45, 0x4E, 0x1A, 0x53];
 const PROGRAM_ROM_UNIT_SIZE: usize = 16384;
 const CHARACTER_ROM_UNIT_SIZE: usize = 8192;
+const PROGRAM_RAM_UNIT_SIZE: usize = 8192;
+const CHARACTER_RAM_SIZE: usize = 8192;
+const TRAINER_SIZE: usize = 512;
+
+/// NES 2.0 ROM size, LSB in the header byte and MSB in a nibble of byte 9.
+/// MSB 0xF means the LSB is 2^E * (MM * 2 + 1) (EEEEEEMM).
+fn rom_size(lsb: u8, msb: u8, unit: usize) -> usize {
+    if msb == 0x0F {
+        let exponent = (lsb >> 2) as u32;
+        let multiplier = (lsb & 0b11) as usize * 2 + 1;
+        2usize.saturating_pow(exponent).saturating_mul(multiplier)
+    } else {
+        ((msb as usize) << 8 | lsb as usize) * unit
+    }
+}
+
+/// inverse of `rom_size`, None when the size cannot be written
+fn rom_size_bytes(size: usize, unit: usize) -> Option<(u8, u8)> {
+    if size.is_multiple_of(unit) && size / unit <= 0xEFF {
+        let units = size / unit;
+        return Some(((units & 0xFF) as u8, (units >> 8) as u8));
+    }
+    for exponent in 0..64u32 {
+        for multiplier in 0..4 {
+            if 2usize
+                .checked_pow(exponent)?
+                .checked_mul(multiplier * 2 + 1)
+                == Some(size)
+            {
+                return Some(((exponent as u8) << 2 | multiplier as u8, 0x0F));
+            }
+        }
+    }
+    None
+}
+
+/// NES 2.0 RAM size, 64 << shift bytes, 0 means none
+fn ram_size(shift: u8) -> usize {
+    if shift == 0 {
+        0
+    } else {
+        64 << shift
+    }
+}
+
+/// inverse of `ram_size`
+fn ram_shift(size: usize) -> anyhow::Result<u8> {
+    match (1..=15).find(|shift| size == 64 << shift) {
+        _ if size == 0 => Ok(0),
+        Some(shift) => Ok(shift),
+        None => Err(anyhow::anyhow!("RAM size {} cannot be written", size)),
+    }
+}
 
 impl INesHeader {
     pub const INES_HEADER_LENGTH: usize = 16;
 
     pub fn parser(data: &[u8]) -> anyhow::Result<INesHeader, anyhow::Error> {
-        if !(data.len() > Self::INES_HEADER_LENGTH && (data[0..4] == MAGIC)
-            || (data[0..4] == MAGIC_SUPER_MARIO) && data[11..16] == [0u8; 5])
-        {
-            return Err(anyhow::anyhow!("not ines format"));
+        if data.len() < Self::INES_HEADER_LENGTH {
+            return Err(anyhow::anyhow!(
+                "not ines format, the header needs {} bytes but the file has {} bytes",
+                Self::INES_HEADER_LENGTH,
+                data.len()
+            ));
+        }
+        if !(data[0..4] == MAGIC || data[0..4] == MAGIC_SUPER_MARIO && data[11..16] == [0u8; 5]) {
+            return Err(anyhow::anyhow!(
+                "not ines format, bad magic {:02X?} (expected {:02X?})",
+                &data[0..4],
+                MAGIC
+            ));
         }
 
-        Ok(INesHeader {
+        if data[7] & 0b0000_1100 == 0b0000_1000 {
+            Ok(Self::parse_nes2(data))
+        } else {
+            Ok(Self::parse_ines(data))
+        }
+    }
+
+    fn parse_common(data: &[u8], flag7: u8) -> INesHeader {
+        let flag6 = data[6];
+        INesHeader {
             magic: MAGIC,
-            program_rom_unit_count: data[4],
-            character_rom_unit_count: data[5],
-            flag6: data[6],
-            flag7: data[7],
-            flag8: data[8],
-            flag9: data[9],
-            flag10: data[10],
-            padding: [0; 5],
-        })
+            nes2: false,
+            program_rom_size: data[4] as usize * PROGRAM_ROM_UNIT_SIZE,
+            character_rom_size: data[5] as usize * CHARACTER_ROM_UNIT_SIZE,
+            mapper: ((flag7 & 0xF0) | (flag6 >> 4)) as u16,
+            submapper: 0,
+            mirroring: if flag6 & 0b0000_0001 == 0 {
+                Mirroring::Horizontal
+            } else {
+                Mirroring::Vertical
+            },
+            battery: flag6 & 0b0000_0010 != 0,
+            trainer: flag6 & 0b0000_0100 != 0,
+            four_screen: flag6 & 0b0000_1000 != 0,
+            console: match flag7 & 0b11 {
+                0 => Console::NES,
+                1 => Console::VsSystem {
+                    ppu: 0,
+                    hardware: 0,
+                },
+                _ => Console::PlayChoice10,
+            },
+            program_ram_size: 0,
+            program_nvram_size: 0,
+            character_ram_size: 0,
+            character_nvram_size: 0,
+            tv_system: TvSystem::NTSC,
+            misc_roms: 0,
+            expansion_device: 0,
+            junk: false,
+        }
     }
 
-    pub fn program_rom_range(&self) -> std::ops::Range<usize> {
+    fn parse_ines(data: &[u8]) -> INesHeader {
+        // old rippers put their name across bytes 7-15 ("DiskDude!"),
+        // the upper nibble of the mapper and the other flags are garbage in that case.
+        let flag = |i: usize| if data[12..16] == [0u8; 4] { data[i] } else { 0 };
+        let header = Self::parse_common(data, flag(7));
+        let program_ram_size = flag(8).max(1) as usize * PROGRAM_RAM_UNIT_SIZE;
+        INesHeader {
+            // battery-backed RAM is the only RAM of iNES
+            program_ram_size: if header.battery { 0 } else { program_ram_size },
+            program_nvram_size: if header.battery { program_ram_size } else { 0 },
+            character_ram_size: if header.character_rom_size == 0 {
+                CHARACTER_RAM_SIZE
+            } else {
+                0
+            },
+            tv_system: if flag(9) & 0b0000_0001 == 0 {
+                TvSystem::NTSC
+            } else {
+                TvSystem::PAL
+            },
+            junk: data[12..16] != [0u8; 4],
+            ..header
+        }
+    }
+
+    fn parse_nes2(data: &[u8]) -> INesHeader {
+        let header = Self::parse_common(data, data[7]);
+        INesHeader {
+            nes2: true,
+            program_rom_size: rom_size(data[4], data[9] & 0x0F, PROGRAM_ROM_UNIT_SIZE),
+            character_rom_size: rom_size(data[5], data[9] >> 4, CHARACTER_ROM_UNIT_SIZE),
+            mapper: header.mapper | ((data[8] & 0x0F) as u16) << 8,
+            submapper: data[8] >> 4,
+            console: match data[7] & 0b11 {
+                0 => Console::NES,
+                1 => Console::VsSystem {
+                    ppu: data[13] & 0x0F,
+                    hardware: data[13] >> 4,
+                },
+                2 => Console::PlayChoice10,
+                _ => Console::Extended(data[13] & 0x0F),
+            },
+            program_ram_size: ram_size(data[10] & 0x0F),
+            program_nvram_size: ram_size(data[10] >> 4),
+            character_ram_size: ram_size(data[11] & 0x0F),
+            character_nvram_size: ram_size(data[11] >> 4),
+            tv_system: match data[12] & 0b11 {
+                0 => TvSystem::NTSC,
+                1 => TvSystem::PAL,
+                2 => TvSystem::Multiple,
+                _ => TvSystem::Dendy,
+            },
+            misc_roms: data[14] & 0b11,
+            expansion_device: data[15] & 0x3F,
+            ..header
+        }
+    }
+
+    /// 512 bytes just after the header, when present
+    pub fn trainer_range(&self) -> Option<std::ops::Range<usize>> {
         let from = Self::INES_HEADER_LENGTH;
+        self.trainer.then(|| from..from + TRAINER_SIZE)
+    }
+
+    pub fn program_rom_range(&self) -> std::ops::Range<usize> {
+        let from = match self.trainer_range() {
+            Some(trainer) => trainer.end,
+            None => Self::INES_HEADER_LENGTH,
+        };
         let to = from + self.program_rom_size();
         from..to
     }
@@ -61,11 +265,412 @@ impl INesHeader {
         from..to
     }
 
-    fn program_rom_size(&self) -> usize {
-        self.program_rom_unit_count as usize * PROGRAM_ROM_UNIT_SIZE
+    pub fn nes2(&self) -> bool {
+        self.nes2
+    }
+
+    pub fn mapper(&self) -> u16 {
+        self.mapper
+    }
+
+    /// always 0 in iNES
+    pub fn submapper(&self) -> u8 {
+        self.submapper
+    }
+
+    /// nametable arrangement hard-wired on the board
+    pub fn mirroring(&self) -> Mirroring {
+        if self.four_screen {
+            Mirroring::FourScreen
+        } else {
+            self.mirroring
+        }
+    }
+
+    /// the board has battery-backed memory
+    pub fn battery(&self) -> bool {
+        self.battery
+    }
+
+    pub fn console(&self) -> Console {
+        self.console
     }
 
-    fn character_rom_size(&self) -> usize {
-        self.character_rom_unit_count as usize * CHARACTER_ROM_UNIT_SIZE
+    pub fn vs_unisystem(&self) -> bool {
+        matches!(self.console, Console::VsSystem { .. })
     }
+
+    pub fn playchoice10(&self) -> bool {
+        self.console == Console::PlayChoice10
+    }
+
+    pub fn tv_system(&self) -> TvSystem {
+        self.tv_system
+    }
+
+    pub fn misc_roms(&self) -> u8 {
+        self.misc_roms
+    }
+
+    pub fn expansion_device(&self) -> u8 {
+        self.expansion_device
+    }
+
+    /// iNES bytes 12-15 are not zero
+    pub fn junk(&self) -> bool {
+        self.junk
+    }
+
+    /// the board has CHR RAM instead of CHR ROM
+    pub fn character_ram(&self) -> bool {
+        self.character_rom_size == 0
+    }
+
+    /// volatile and non-volatile PRG RAM
+    pub fn program_ram_size(&self) -> usize {
+        self.program_ram_size + self.program_nvram_size
+    }
+
+    pub fn program_nvram_size(&self) -> usize {
+        self.program_nvram_size
+    }
+
+    /// volatile and non-volatile CHR RAM
+    pub fn character_ram_size(&self) -> usize {
+        self.character_ram_size + self.character_nvram_size
+    }
+
+    pub fn program_rom_size(&self) -> usize {
+        self.program_rom_size
+    }
+
+    pub fn character_rom_size(&self) -> usize {
+        self.character_rom_size
+    }
+
+    /// iNES header of NROM, PRG RAM is 8 KB as iNES infers
+    pub fn new(program_rom_size: usize, character_rom_size: usize) -> Self {
+        INesHeader {
+            magic: MAGIC,
+            nes2: false,
+            program_rom_size,
+            character_rom_size,
+            mapper: 0,
+            submapper: 0,
+            mirroring: Mirroring::Horizontal,
+            battery: false,
+            trainer: false,
+            four_screen: false,
+            console: Console::NES,
+            program_ram_size: PROGRAM_RAM_UNIT_SIZE,
+            program_nvram_size: 0,
+            character_ram_size: if character_rom_size == 0 {
+                CHARACTER_RAM_SIZE
+            } else {
+                0
+            },
+            character_nvram_size: 0,
+            tv_system: TvSystem::NTSC,
+            misc_roms: 0,
+            expansion_device: 0,
+            junk: false,
+        }
+    }
+
+    pub fn set_mapper(&mut self, mapper: u16, submapper: u8) {
+        self.mapper = mapper;
+        self.submapper = submapper;
+    }
+
+    pub fn set_mirroring(&mut self, mirroring: Mirroring) {
+        self.four_screen = mirroring == Mirroring::FourScreen;
+        if !self.four_screen {
+            self.mirroring = mirroring;
+        }
+    }
+
+    /// RAM of the board is moved to the battery-backed side, and vice versa
+    pub fn set_battery(&mut self, battery: bool) {
+        let ram = self.program_ram_size();
+        self.battery = battery;
+        self.program_ram_size = if battery { 0 } else { ram };
+        self.program_nvram_size = if battery { ram } else { 0 };
+    }
+
+    pub fn set_tv_system(&mut self, tv_system: TvSystem) {
+        self.tv_system = tv_system;
+    }
+
+    pub fn set_expansion_device(&mut self, expansion_device: u8) {
+        self.expansion_device = expansion_device;
+    }
+
+    /// the same header in NES 2.0 format
+    pub fn to_nes2(self) -> Self {
+        INesHeader {
+            nes2: true,
+            ..self
+        }
+    }
+
+    /// 16 bytes of the header, fails when a value cannot be written in the format
+    pub fn to_bytes(self) -> anyhow::Result<[u8; 16]> {
+        if self.nes2 {
+            self.to_nes2_bytes()
+        } else {
+            self.to_ines_bytes()
+        }
+    }
+
+    fn flags(&self) -> [u8; 2] {
+        let flag6 = ((self.mapper & 0x0F) as u8) << 4
+            | (self.four_screen as u8) << 3
+            | (self.trainer as u8) << 2
+            | (self.battery as u8) << 1
+            | (self.mirroring == Mirroring::Vertical) as u8;
+        let console = match self.console {
+            Console::NES => 0,
+            Console::VsSystem { .. } => 1,
+            Console::PlayChoice10 => 2,
+            Console::Extended(_) => 3,
+        };
+        let flag7 = (self.mapper & 0xF0) as u8 | console;
+        [flag6, flag7]
+    }
+
+    fn to_ines_bytes(self) -> anyhow::Result<[u8; 16]> {
+        let units = |size: usize, unit: usize, name: &str| {
+            if size.is_multiple_of(unit) && size / unit <= 0xFF {
+                Ok((size / unit) as u8)
+            } else {
+                Err(anyhow::anyhow!(
+                    "{} size {} cannot be written in iNES, use NES 2.0",
+                    name,
+                    size
+                ))
+            }
+        };
+        if self.mapper > 0xFF || self.submapper != 0 {
+            return Err(anyhow::anyhow!(
+                "mapper {}.{} cannot be written in iNES, use NES 2.0",
+                self.mapper,
+                self.submapper
+            ));
+        }
+        if let Console::Extended(_) = self.console {
+            return Err(anyhow::anyhow!(
+                "extended console type cannot be written in iNES, use NES 2.0"
+            ));
+        }
+
+        let [flag6, flag7] = self.flags();
+        let program_ram = match units(self.program_ram_size(), PROGRAM_RAM_UNIT_SIZE, "PRG RAM")? {
+            // 0 infers 8 KB
+            1 => 0,
+            n => n,
+        };
+        let mut bytes = [0; 16];
+        bytes[0..4].copy_from_slice(&MAGIC);
+        bytes[4] = units(self.program_rom_size, PROGRAM_ROM_UNIT_SIZE, "PRG ROM")?;
+        bytes[5] = units(self.character_rom_size, CHARACTER_ROM_UNIT_SIZE, "CHR ROM")?;
+        bytes[6] = flag6;
+        bytes[7] = flag7;
+        bytes[8] = program_ram;
+        bytes[9] = (self.tv_system == TvSystem::PAL) as u8;
+        Ok(bytes)
+    }
+
+    fn to_nes2_bytes(self) -> anyhow::Result<[u8; 16]> {
+        let (program_lsb, program_msb) =
+            rom_size_bytes(self.program_rom_size, PROGRAM_ROM_UNIT_SIZE).ok_or_else(|| {
+                anyhow::anyhow!("PRG ROM size {} cannot be written", self.program_rom_size)
+            })?;
+        let (character_lsb, character_msb) =
+            rom_size_bytes(self.character_rom_size, CHARACTER_ROM_UNIT_SIZE).ok_or_else(|| {
+                anyhow::anyhow!("CHR ROM size {} cannot be written", self.character_rom_size)
+            })?;
+        let [flag6, flag7] = self.flags();
+        let mut bytes = [0; 16];
+        bytes[0..4].copy_from_slice(&MAGIC);
+        bytes[4] = program_lsb;
+        bytes[5] = character_lsb;
+        bytes[6] = flag6;
+        bytes[7] = flag7 | 0b0000_1000;
+        bytes[8] = self.submapper << 4 | (self.mapper >> 8) as u8 & 0x0F;
+        bytes[9] = character_msb << 4 | program_msb;
+        bytes[10] = ram_shift(self.program_nvram_size)? << 4 | ram_shift(self.program_ram_size)?;
+        bytes[11] =
+            ram_shift(self.character_nvram_size)? << 4 | ram_shift(self.character_ram_size)?;
+        bytes[12] = match self.tv_system {
+            TvSystem::NTSC => 0,
+            TvSystem::PAL => 1,
+            TvSystem::Multiple => 2,
+            TvSystem::Dendy => 3,
+        };
+        bytes[13] = match self.console {
+            Console::VsSystem { ppu, hardware } => hardware << 4 | ppu,
+            Console::Extended(n) => n,
+            _ => 0,
+        };
+        bytes[14] = self.misc_roms;
+        bytes[15] = self.expansion_device;
+        Ok(bytes)
+    }
+}
+
+impl std::fmt::Display for INesHeader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let yes = |v: bool| if v { "yes" } else { "no" };
+        writeln!(
+            f,
+            "format    : {}",
+            if self.nes2 { "NES 2.0" } else { "iNES" }
+        )?;
+        writeln!(f, "mapper    : {}.{}", self.mapper, self.submapper)?;
+        writeln!(f, "PRG ROM   : {} KB", self.program_rom_size / 1024)?;
+        writeln!(f, "CHR ROM   : {} KB", self.character_rom_size / 1024)?;
+        writeln!(
+            f,
+            "PRG RAM   : {} KB (battery-backed {} KB)",
+            self.program_ram_size() / 1024,
+            self.program_nvram_size / 1024
+        )?;
+        writeln!(f, "CHR RAM   : {} KB", self.character_ram_size() / 1024)?;
+        writeln!(f, "mirroring : {:?}", self.mirroring())?;
+        writeln!(f, "battery   : {}", yes(self.battery))?;
+        writeln!(f, "trainer   : {}", yes(self.trainer))?;
+        writeln!(f, "console   : {:?}", self.console)?;
+        writeln!(f, "TV system : {:?}", self.tv_system)?;
+        writeln!(f, "misc ROMs : {}", self.misc_roms)?;
+        write!(f, "expansion : 0x{:02X}", self.expansion_device)
+    }
+}
+
+#[cfg(test)]
+fn header(flags: [u8; 12]) -> Vec<u8> {
+    let mut data = MAGIC.to_vec();
+    data.extend_from_slice(&flags);
+    data.push(0);
+    data
+}
+
+#[test]
+fn it_flags() {
+    let h = INesHeader::parser(&header([2, 1, 0x4B, 0x11, 2, 1, 0, 0, 0, 0, 0, 0])).unwrap();
+    assert!(!h.nes2());
+    assert_eq!(h.mapper(), 0x14);
+    assert_eq!(h.mirroring(), Mirroring::FourScreen);
+    assert!(h.battery());
+    assert!(h.vs_unisystem());
+    assert!(!h.playchoice10());
+    assert_eq!(h.program_ram_size(), 16384);
+    assert_eq!(h.program_nvram_size(), 16384);
+    assert_eq!(h.tv_system(), TvSystem::PAL);
+    assert!(!h.character_ram());
+}
+
+#[test]
+fn it_trainer() {
+    let h = INesHeader::parser(&header([1, 0, 0b0100, 0, 0, 0, 0, 0, 0, 0, 0, 0])).unwrap();
+    assert_eq!(h.trainer_range(), Some(16..528));
+    assert_eq!(h.program_rom_range(), 528..528 + 16384);
+    assert_eq!(h.character_rom_range(), 528 + 16384..528 + 16384);
+    assert_eq!(h.character_ram_size(), 8192);
+}
+
+#[test]
+fn it_ignore_garbage_in_padding() {
+    let mut data = header([1, 1, 0x10, 0x40, 0, 0, 0, 0, 0, 0, 0, 0]);
+    data[7..16].copy_from_slice(b"DiskDude!");
+    let h = INesHeader::parser(&data).unwrap();
+    assert_eq!(h.mapper(), 0x01);
+    assert!(h.junk());
+}
+
+#[test]
+fn it_nes2() {
+    let h = INesHeader::parser(&header([
+        0x02, 0x00, 0x50, 0x49, 0x31, 0x01, 0x77, 0x07, 0x03, 0x01, 0x02, 0x2A,
+    ]))
+    .unwrap();
+    assert!(h.nes2());
+    assert_eq!(h.mapper(), 0x145);
+    assert_eq!(h.submapper(), 3);
+    assert_eq!(h.program_rom_size(), 0x102 * 16384);
+    assert_eq!(h.program_ram_size(), (64 << 7) + (64 << 7));
+    assert_eq!(h.character_ram_size(), 64 << 7);
+    assert_eq!(h.tv_system(), TvSystem::Dendy);
+    assert_eq!(
+        h.console(),
+        Console::VsSystem {
+            ppu: 1,
+            hardware: 0
+        }
+    );
+    assert_eq!(h.misc_roms(), 2);
+    assert_eq!(h.expansion_device(), 0x2A);
+}
+
+#[test]
+fn it_nes2_exponent_size() {
+    // 2^10 * 3 bytes
+    let h = INesHeader::parser(&header([
+        0b0010_1001,
+        0,
+        0,
+        0x08,
+        0,
+        0x0F,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+    ]))
+    .unwrap();
+    assert_eq!(h.program_rom_size(), 1024 * 3);
+}
+
+#[test]
+fn it_round_trip_ines() {
+    let data = header([2, 1, 0x4B, 0x11, 2, 1, 0, 0, 0, 0, 0, 0]);
+    let h = INesHeader::parser(&data).unwrap();
+    let bytes = h.to_bytes().unwrap();
+    assert_eq!(bytes[..], data[..16]);
+    assert_eq!(INesHeader::parser(&[&bytes[..], &[0]].concat()).unwrap(), h);
+}
+
+#[test]
+fn it_round_trip_nes2() {
+    let data = header([
+        0x02, 0x00, 0x50, 0x49, 0x31, 0x01, 0x77, 0x07, 0x03, 0x01, 0x02, 0x2A,
+    ]);
+    let h = INesHeader::parser(&data).unwrap();
+    assert_eq!(h.to_bytes().unwrap()[..], data[..16]);
+
+    let data = header([0b0010_1001, 0, 0, 0x08, 0, 0x0F, 0, 0, 0, 0, 0, 0]);
+    let h = INesHeader::parser(&data).unwrap();
+    assert_eq!(h.to_bytes().unwrap()[..], data[..16]);
+}
+
+#[test]
+fn it_convert_to_nes2() {
+    let mut h = INesHeader::new(32768, 8192);
+    h.set_mapper(4, 0);
+    h.set_mirroring(Mirroring::Vertical);
+    h.set_battery(true);
+    let ines = h.to_bytes().unwrap();
+    let nes2 = h.to_nes2();
+    let bytes = nes2.to_bytes().unwrap();
+    assert_eq!(bytes[7] & 0b1100, 0b1000);
+    let parsed = INesHeader::parser(&[&bytes[..], &[0]].concat()).unwrap();
+    assert_eq!(parsed, nes2);
+    assert_eq!(parsed.program_nvram_size(), 8192);
+    assert_eq!(bytes[4..7], ines[4..7]);
+
+    // not representable in iNES
+    h.set_mapper(300, 0);
+    assert!(h.to_bytes().is_err());
+    assert!(h.to_nes2().to_bytes().is_ok());
 }
@@ -9,22 +9,90 @@ pub struct INes<'a> {
 
 impl<'a> INes<'a> {
     pub fn parse(raw: &'a [u8]) -> anyhow::Result<INes<'a>, anyhow::Error> {
-        let header = INesHeader::parser(&raw)?;
+        let header = INesHeader::parser(raw)?;
+        if header.program_rom_size() == 0 {
+            return Err(anyhow::anyhow!("PRG ROM size is 0"));
+        }
+
+        // every section must be inside the file before slicing it
+        let sections = [
+            ("trainer", header.trainer_range().map_or(0, |r| r.len())),
+            ("PRG ROM", header.program_rom_size()),
+            ("CHR ROM", header.character_rom_size()),
+        ];
+        let mut offset = INesHeader::INES_HEADER_LENGTH;
+        for (name, size) in sections {
+            offset = match offset.checked_add(size) {
+                Some(end) if end <= raw.len() => end,
+                _ => {
+                    return Err(anyhow::anyhow!(
+                        "truncated file, {} needs {} bytes at offset {} but {} bytes are left",
+                        name,
+                        size,
+                        offset,
+                        raw.len() - offset
+                    ))
+                }
+            };
+        }
         Ok(INes { header, raw })
     }
 
+    pub fn header(&self) -> &INesHeader {
+        &self.header
+    }
+
+    /// sizes must not be changed, the sections are sliced with them
+    pub fn header_mut(&mut self) -> &mut INesHeader {
+        &mut self.header
+    }
+
+    /// everything after the header
+    pub fn body(&self) -> &[u8] {
+        &self.raw[INesHeader::INES_HEADER_LENGTH..]
+    }
+
+    pub fn trainer(&self) -> Option<&[u8]> {
+        self.header.trainer_range().map(|range| &self.raw[range])
+    }
+
     pub fn program(&self) -> &[u8] {
         &self.raw[self.header.program_rom_range()]
     }
 
+    pub fn character(&self) -> &[u8] {
+        &self.raw[self.header.character_rom_range()]
+    }
+
     pub fn sprites(&self) -> SpriteROM {
-        let raw = &self.raw[self.header.character_rom_range()];
-        SpriteROM::new(raw)
+        SpriteROM::new(self.character())
     }
 }
 
 impl<'a> std::fmt::Display for INes<'a> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self.header)
+        write!(f, "{}", self.header)
     }
 }
+
+#[test]
+fn it_parse_errors() {
+    let message = |data: &[u8]| INes::parse(data).unwrap_err().to_string();
+    assert!(message(&[0x4E, 0x45]).contains("header needs 16 bytes"));
+    assert!(message(&[0; 16]).contains("bad magic"));
+
+    let mut data = vec![0x4E, 0x45, 0x53, 0x1A, 2, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    data.extend_from_slice(&[0; 0x4000]);
+    assert_eq!(
+        message(&data),
+        "truncated file, PRG ROM needs 32768 bytes at offset 16 but 16384 bytes are left"
+    );
+    data.extend_from_slice(&[0; 0x4000]);
+    assert!(message(&data).contains("CHR ROM needs 8192 bytes"));
+    data.extend_from_slice(&[0; 0x2000]);
+    assert!(INes::parse(&data).is_ok());
+
+    // NES 2.0 exponent size overflows the address space
+    let data = [0x4E, 0x45, 0x53, 0x1A, 0xFF, 0, 0, 0x08, 0, 0x0F, 0, 0, 0, 0, 0, 0];
+    assert!(message(&data).contains("truncated file"));
+}
@@ -2,5 +2,6 @@ mod header;
 mod ines;
 mod sprite_rom;
 
+pub use header::{INesHeader, TvSystem};
 pub use ines::INes;
 pub use sprite_rom::SpriteROM;
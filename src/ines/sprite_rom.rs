@@ -11,14 +11,16 @@ impl SpriteROM {
         &self.rom
     }
 
-    pub fn new<'a>(raw: &'a [u8]) -> Self {
-        let mut rom = vec![];
-        for i in 0..(raw.len() / 16) {
-            let from = i * 16;
-            let to = from + 16;
-            let s = &raw[from..to];
-            rom.push(Sprite::new(&s.try_into().unwrap()));
-        }
+    /// a partial tile at the end is filled with zero
+    pub fn new(raw: &[u8]) -> Self {
+        let rom = raw
+            .chunks(16)
+            .map(|chunk| {
+                let mut s = [0; 16];
+                s[..chunk.len()].copy_from_slice(chunk);
+                Sprite::new(&s)
+            })
+            .collect();
         Self { rom }
     }
 }
@@ -30,3 +32,9 @@ impl std::ops::Index<usize> for SpriteROM {
         &self.rom[index]
     }
 }
+
+impl std::ops::IndexMut<usize> for SpriteROM {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        &mut self.rom[index]
+    }
+}
@@ -0,0 +1,215 @@
+use crate::cartridge;
+use crate::db::{hex, Database, Entry, Hashes};
+use crate::ines::{INes, INesHeader};
+use crate::result::Result;
+use crate::unif;
+use serde_json::json;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(clap::Args, Debug)]
+pub struct Command {
+    input: PathBuf,
+
+    /// print the report as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+pub fn run(command: &Command, database: Option<&Database>) -> Result<()> {
+    let data = unif::load(fs::read(&command.input)?)?;
+    let ines = INes::parse(&data)?;
+    let report = Report::new(&ines, database);
+    let file = command.input.display();
+    if command.json {
+        let mut value = report.to_json();
+        value["file"] = json!(file.to_string());
+        println!("{}", serde_json::to_string_pretty(&value)?);
+    } else {
+        println!("file      : {}", file);
+        println!("{}", report);
+    }
+    Ok(())
+}
+
+/// what the header says about a ROM, with the database match and sanity warnings
+#[derive(Debug)]
+pub struct Report {
+    header: INesHeader,
+    hashes: Hashes,
+    /// None when the database is not used or has no match
+    entry: Option<Entry>,
+    /// None when the mapper is supported
+    unsupported: Option<String>,
+    warnings: Vec<String>,
+}
+
+impl Report {
+    pub fn new(ines: &INes, database: Option<&Database>) -> Self {
+        let header = *ines.header();
+        let hashes = Hashes::new(ines.program(), ines.character());
+        let entry = database.and_then(|db| db.find(&hashes)).cloned();
+        let unsupported = cartridge::load(ines).err().map(|err| err.to_string());
+
+        let mut warnings = vec![];
+        if header.junk() {
+            warnings.push("bytes 12-15 of the header are not zero, flags 7-9 were ignored".into());
+        }
+        let sizes = header.trainer_range().map_or(0, |r| r.len())
+            + header.program_rom_size()
+            + header.character_rom_size();
+        let extra = ines.body().len() - sizes;
+        if extra > 0 && header.misc_roms() == 0 {
+            warnings.push(format!(
+                "size mismatch, {} bytes after CHR ROM are not in the header",
+                extra
+            ));
+        }
+        if cartridge::mapper_name(header.mapper()).is_none() {
+            warnings.push(format!("unknown mapper {}", header.mapper()));
+        }
+        if let Some(entry) = &entry {
+            let mut differ = |name: &str, header: String, db: Option<String>| match db {
+                Some(db) if db != header => warnings.push(format!(
+                    "{} is {} in the header but {} in the database",
+                    name, header, db
+                )),
+                _ => {}
+            };
+            differ(
+                "mapper",
+                header.mapper().to_string(),
+                entry.mapper.map(|v| v.to_string()),
+            );
+            differ(
+                "submapper",
+                header.submapper().to_string(),
+                entry
+                    .mapper
+                    .map(|_| entry.submapper.unwrap_or(0).to_string()),
+            );
+            differ(
+                "mirroring",
+                format!("{:?}", header.mirroring()),
+                entry.mirroring.map(|v| format!("{:?}", v)),
+            );
+            differ(
+                "region",
+                format!("{:?}", header.tv_system()),
+                entry.tv_system.map(|v| format!("{:?}", v)),
+            );
+            differ(
+                "battery",
+                header.battery().to_string(),
+                entry.battery.map(|v| v.to_string()),
+            );
+        }
+
+        Self {
+            header,
+            hashes,
+            entry,
+            unsupported,
+            warnings,
+        }
+    }
+
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let h = &self.header;
+        let debug = |v: &dyn std::fmt::Debug| format!("{:?}", v);
+        json!({
+            "format": if h.nes2() { "NES 2.0" } else { "iNES" },
+            "mapper": h.mapper(),
+            "mapper_name": cartridge::mapper_name(h.mapper()),
+            "submapper": h.submapper(),
+            "supported": self.unsupported.is_none(),
+            "prg_rom": h.program_rom_size(),
+            "chr_rom": h.character_rom_size(),
+            "prg_ram": h.program_ram_size(),
+            "prg_nvram": h.program_nvram_size(),
+            "chr_ram": h.character_ram_size(),
+            "mirroring": debug(&h.mirroring()),
+            "battery": h.battery(),
+            "trainer": h.trainer_range().is_some(),
+            "console": debug(&h.console()),
+            "region": debug(&h.tv_system()),
+            "expansion_device": h.expansion_device(),
+            "hashes": {
+                "crc32": format!("{:08X}", self.hashes.crc32),
+                "md5": hex(&self.hashes.md5),
+                "sha1": hex(&self.hashes.sha1),
+            },
+            "database": self.entry.as_ref().map(|entry| json!({
+                "name": entry.name,
+                "mapper": entry.mapper,
+                "submapper": entry.submapper,
+                "mirroring": entry.mirroring.map(|v| debug(&v)),
+                "region": entry.tv_system.map(|v| debug(&v)),
+                "battery": entry.battery,
+                "expansion_device": entry.expansion_device,
+            })),
+            "warnings": self.warnings,
+        })
+    }
+}
+
+impl std::fmt::Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mapper = self.header.mapper();
+        writeln!(f, "{}", self.header)?;
+        writeln!(
+            f,
+            "board     : {}{}",
+            cartridge::mapper_name(mapper).unwrap_or("unknown"),
+            if self.unsupported.is_some() {
+                " (not supported)"
+            } else {
+                ""
+            }
+        )?;
+        writeln!(f, "{}", self.hashes)?;
+        match &self.entry {
+            Some(entry) => write!(
+                f,
+                "database  : {}",
+                entry.name.as_deref().unwrap_or("(no name)")
+            )?,
+            None => write!(f, "database  : no match")?,
+        }
+        for warning in &self.warnings {
+            write!(f, "\nwarning   : {}", warning)?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn it_warnings() {
+    let mut data = vec![
+        0x4E, 0x45, 0x53, 0x1A, 1, 0, 0xF0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    ];
+    data[7..16].copy_from_slice(b"DiskDude!");
+    data.extend_from_slice(&[0; 0x4000 + 16]);
+    let ines = INes::parse(&data).unwrap();
+    let database = Database::parse_csv(&format!(
+        "crc32,mapper,mirroring\n{:08X},2,V\n",
+        Hashes::new(ines.program(), ines.character()).crc32
+    ))
+    .unwrap();
+    let report = Report::new(&ines, Some(&database));
+    let warnings = report.warnings().join("\n");
+    assert!(warnings.contains("flags 7-9 were ignored"), "{}", warnings);
+    assert!(warnings.contains("16 bytes after CHR ROM"), "{}", warnings);
+    assert!(warnings.contains("unknown mapper 15"), "{}", warnings);
+    assert!(warnings.contains("mapper is 15 in the header but 2 in the database"));
+    assert!(warnings.contains("mirroring is Horizontal in the header but Vertical"));
+
+    let json = report.to_json();
+    assert_eq!(json["mapper"], 15);
+    assert_eq!(json["supported"], false);
+    assert_eq!(json["database"]["mapper"], 2);
+}
@@ -0,0 +1,3 @@
+mod info;
+
+pub use info::{run, Command, Report};
@@ -0,0 +1,22 @@
+pub mod array2;
+pub mod audio;
+pub mod bits;
+pub mod cartridge;
+pub mod cpu;
+pub mod db;
+pub mod display;
+pub mod fds;
+pub mod info;
+pub mod ines;
+pub mod memory;
+pub mod ppu;
+pub mod program;
+pub mod rect;
+pub mod region;
+pub mod result;
+pub mod rom;
+pub mod save;
+pub mod sprite;
+pub mod unif;
+pub mod vec2;
+pub mod x;
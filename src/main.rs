@@ -1,22 +1,11 @@
-mod array2;
-mod bits;
-mod cpu;
-mod display;
-mod ines;
-mod memory;
-mod ppu;
-mod program;
-mod rect;
-mod result;
-mod sprite;
-mod vec2;
-mod x;
-
 use clap::Parser;
-use result::Result;
+use fc::result::Result;
+use fc::{
+    audio, cartridge, cpu, db, display, fds, ines, info, memory, ppu, region, rom, save, unif,
+};
 
 use std::fs;
-use std::io::Read;
+use std::path::Path;
 
 use std::cell::RefCell;
 use std::rc::Rc;
@@ -35,28 +24,97 @@ fn window_conf() -> Conf {
 }
 
 #[derive(Parser)]
-#[command()]
+#[command(subcommand_negates_reqs = true)]
 struct CLI {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     #[arg(long)]
     debug: bool,
 
-    #[arg(short)]
-    nes: std::path::PathBuf,
+    #[arg(short, required = true)]
+    nes: Option<std::path::PathBuf>,
+
+    /// directory of .sav files, next to the ROM by default
+    #[arg(long)]
+    save_dir: Option<std::path::PathBuf>,
+
+    /// FDS BIOS, disksys.rom next to the disk image by default
+    #[arg(long)]
+    bios: Option<std::path::PathBuf>,
+
+    /// record the sound of the cartridge to a WAV file, there is no audio device output
+    #[arg(long)]
+    wav: Option<std::path::PathBuf>,
+
+    /// game database (nes20db.xml or .csv), ./nes20db.xml is used if it exists
+    #[arg(long, global = true)]
+    db: Option<std::path::PathBuf>,
+
+    /// trust the header, the database does not override it
+    #[arg(long, global = true)]
+    no_db: bool,
+
+    /// console timing, the header and the database choose it by default
+    #[arg(long, value_enum)]
+    region: Option<region::Region>,
 }
 
-#[macroquad::main(window_conf)]
-async fn main() -> Result<()> {
+#[derive(clap::Subcommand)]
+enum Command {
+    /// tools for .nes files
+    #[command(subcommand)]
+    Rom(rom::Command),
+
+    /// print what the header and the database say about a .nes file
+    Info(info::Command),
+}
+
+/// battery-backed memory is written every 5 seconds
+const SAVE_INTERVAL_SECONDS: f64 = 5.0;
+
+fn main() -> Result<()> {
     let cli = CLI::parse();
+    match &cli.command {
+        Some(Command::Rom(command)) => rom::run(command),
+        Some(Command::Info(command)) => info::run(command, database(&cli)?.as_ref()),
+        // the window is opened only for the emulator
+        None => {
+            macroquad::Window::from_config(window_conf(), async move {
+                if let Err(err) = emulate(cli).await {
+                    eprintln!("{:?}", err);
+                    std::process::exit(1);
+                }
+            });
+            Ok(())
+        }
+    }
+}
 
-    let mut f = fs::File::open(&cli.nes)?;
-    let length = f.metadata()?.len() as usize;
-    let mut data = vec![0; length];
-    let n = f.read(&mut data)?;
-    let data = &data[..n];
+/// None with --no-db
+fn database(cli: &CLI) -> Result<Option<db::Database>> {
+    if cli.no_db {
+        return Ok(None);
+    }
+    db::Database::open(cli.db.as_deref())
+}
 
-    let ines = ines::INes::parse(data)?;
+/// .nes or .unf file, the header is corrected by the database
+fn load_rom(cli: &CLI, data: Vec<u8>) -> Result<(cartridge::Cartridge, ines::TvSystem)> {
+    let data = unif::load(data)?;
+    let mut ines = ines::INes::parse(&data)?;
+    let hashes = db::Hashes::new(ines.program(), ines.character());
+    println!("{}", hashes);
+    if let Some(database) = database(cli)? {
+        if let Some(entry) = database.find(&hashes) {
+            println!(
+                "database  : {}",
+                entry.name.as_deref().unwrap_or("(no name)")
+            );
+            entry.apply(ines.header_mut());
+        }
+    }
     println!("{}", ines);
-    let display = Rc::new(RefCell::new(display::Display::default()));
 
     // program::debug_program(ines.program());
     // panic!();
@@ -64,62 +122,180 @@ async fn main() -> Result<()> {
     // sprite::debug_sprite(ines.sprites());
     // panic!();
 
+    Ok((cartridge::load(&ines)?, ines.header().tv_system()))
+}
+
+/// .fds or QD image with the BIOS
+fn load_disk(cli: &CLI, path: &Path, data: &[u8]) -> Result<cartridge::Cartridge> {
+    let disk = fds::DiskImage::parse(data)?;
+    println!("{}", disk);
+    let bios = cli
+        .bios
+        .clone()
+        .unwrap_or_else(|| path.with_file_name(fds::BIOS_FILE_NAME));
+    let bios = fs::read(&bios).map_err(|err| {
+        anyhow::anyhow!("FDS BIOS {}: {}, give it with --bios", bios.display(), err)
+    })?;
+    cartridge::load_fds(&disk, &bios)
+}
+
+async fn emulate(cli: CLI) -> Result<()> {
+    let nes = cli.nes.as_ref().expect("required without a subcommand");
+    let data = fs::read(nes)?;
+    let mut save = save::SaveFile::new(nes, cli.save_dir.as_deref());
+    let (cartridge, tv_system) = if fds::DiskImage::is_fds(&data) {
+        // writes to the disk go to a patch, the image is kept as it is
+        save = save.with_extension("ips");
+        (load_disk(&cli, nes, &data)?, ines::TvSystem::NTSC)
+    } else {
+        load_rom(&cli, data)?
+    };
+    let region = cli.region.unwrap_or(tv_system.into());
+    println!("region    : {:?}", region);
+    let display = Rc::new(RefCell::new(display::Display::default()));
+
+    let saved = cartridge.borrow().battery().is_some() || cartridge.borrow().disk_sides() > 0;
+    if saved {
+        if let Some(data) = save.load()? {
+            cartridge.borrow_mut().load_battery(&data);
+        }
+    }
+
     let ppu_register = RefCell::new(ppu::Register::default());
-    let ppu_memory = ppu::MemoryMap::new(ines.sprites());
-    let ppu = Rc::new(RefCell::new(ppu::PPU::new(
-        ppu_register,
-        ppu_memory,
-        Rc::clone(&display),
-    )));
+    let ppu_memory = ppu::MemoryMap::new(Rc::clone(&cartridge));
+    let ppu = Rc::new(RefCell::new(
+        ppu::PPU::new(ppu_register, ppu_memory, Rc::clone(&display)).with_region(region),
+    ));
 
     let wram = vec![0; 0x2000];
-    let exrom = vec![0; 0x5FFF - 0x4020];
-    let exram = vec![0; 0x7FFF - 0x6000];
     let apu = vec![0; 0x401F - 0x4000];
 
     let cpu_register = cpu::Register::default();
-    let cpu_memory = cpu::MemoryMap::new(
-        Rc::clone(&ppu),
-        ines.program().to_vec(),
-        wram,
-        apu,
-        exram,
-        exrom,
-    );
+    let cpu_memory = cpu::MemoryMap::new(Rc::clone(&ppu), Rc::clone(&cartridge), wram, apu);
     let mut cpu = cpu::CPU::new(cpu_register, cpu_memory);
 
+    let mixer = audio::Mixer::new(region.cpu_clock());
+
     cpu.reset()?;
-    app(&cli, &mut cpu, ppu, display).await?;
+    let result = app(
+        &cli,
+        &mut cpu,
+        ppu,
+        Rc::clone(&cartridge),
+        display,
+        mixer,
+        &mut save,
+    )
+    .await;
+    // in-game saves since the last flush are kept when the emulation fails too
+    let flushed = flush_battery(&cartridge, &mut save);
+    result.and(flushed)
+}
 
+/// eject the disk and insert the next side
+fn switch_disk(cartridge: &cartridge::Cartridge) {
+    let sides = cartridge.borrow().disk_sides();
+    if sides == 0 {
+        return;
+    }
+    let next = cartridge.borrow().disk().map_or(0, |n| (n + 1) % sides);
+    cartridge.borrow_mut().insert_disk(Some(next));
+    println!("disk side {}", next);
+}
+
+fn flush_battery(cartridge: &cartridge::Cartridge, save: &mut save::SaveFile) -> Result<()> {
+    if let Some(data) = cartridge.borrow().battery() {
+        save.flush(&data)?;
+    }
     Ok(())
 }
 
 async fn app<
     CPUM: memory::RAM<usize, Input = u8, Output = u8>
         + memory::ROM<[usize; 2], Output = u16>
+        + memory::DMA
         + std::fmt::Display,
 >(
     cli: &CLI,
     cpu: &mut cpu::CPU<CPUM>,
     ppu: Rc<RefCell<ppu::PPU>>,
+    cartridge: cartridge::Cartridge,
     display: Rc<RefCell<display::Display>>,
+    mut mixer: audio::Mixer,
+    save: &mut save::SaveFile,
 ) -> Result<()> {
+    let mut frame = 0;
+    let region = ppu.borrow().region();
+    let save_interval = (region.frame_rate() * SAVE_INTERVAL_SECONDS) as usize;
+    // fraction of the PPU dots left by the CPU cycles
+    let mut dots = 0;
+    // frames to run, the window refreshes at its own rate
+    let mut pending = 0.0;
+    let mut wav = cli
+        .wav
+        .as_deref()
+        .map(audio::WavWriter::create)
+        .transpose()?;
+    // flush the save file before the window is closed
+    macroquad::input::prevent_quit();
+
     // for _ in 0..3 {
     loop {
         if macroquad::input::is_key_pressed(macroquad::input::KeyCode::Space) {
             cpu.reset()?;
         }
+        if macroquad::input::is_key_pressed(macroquad::input::KeyCode::Tab) {
+            switch_disk(&cartridge);
+        }
+        let quit = macroquad::input::is_quit_requested();
 
-        loop {
-            println!("------------------");
-            let cycle = cpu.exec(cli.debug)?;
-            let drawed = ppu.borrow_mut().exec(cycle * 3)?;
-            if cli.debug {
-                println!("{}", cpu);
+        // frames follow the refresh rate of the region, not the one of the window
+        let elapsed = macroquad::time::get_frame_time() as f64;
+        pending = (pending + elapsed * region.frame_rate()).min(2.0);
+        while pending >= 1.0 {
+            pending -= 1.0;
+            loop {
+                if cli.debug {
+                    println!("------------------");
+                }
+                // the interrupts are taken before the next instruction, in the same cycles
+                let mut cycle = 0;
+                if ppu.borrow().nmi() {
+                    cycle += cpu.nmi()?;
+                }
+                if cartridge.borrow().irq() {
+                    cycle += cpu.irq()?;
+                }
+                cycle += cpu.exec(cli.debug)?;
+                let drawed = ppu.borrow_mut().exec(region.dots(cycle, &mut dots))?;
+                for _ in 0..cycle {
+                    cartridge.borrow_mut().clock();
+                    mixer.push(cartridge.borrow().sample());
+                }
+                if cli.debug {
+                    println!("{}", cpu);
+                }
+                if drawed {
+                    break;
+                }
             }
-            if drawed {
-                break;
+
+            let samples = mixer.drain();
+            if let Some(wav) = &mut wav {
+                wav.write(&samples)?;
+            }
+
+            frame += 1;
+            if frame % save_interval == 0 {
+                flush_battery(&cartridge, save)?;
+            }
+        }
+        if quit {
+            flush_battery(&cartridge, save)?;
+            if let Some(wav) = wav.take() {
+                wav.finish()?;
             }
+            break;
         }
 
         let image = &display.borrow().image;
@@ -15,6 +15,12 @@ pub trait WOM<Idx: Sized> {
 
 pub trait RAM<Idx: Sized>: ROM<Idx> + WOM<Idx> {}
 
+/// memory copied by DMA, the CPU halts while it runs
+pub trait DMA {
+    /// returns true once after a DMA
+    fn take_dma(&mut self) -> bool;
+}
+
 /**
  * Vec<u8>
  */
@@ -1,3 +1,5 @@
+use crate::bits::Byte;
+
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
 pub struct Color {
     pub r: u8,
@@ -5,6 +7,27 @@ pub struct Color {
     pub b: u8,
 }
 
+/// each emphasized channel darkens the other two
+const ATTENUATION: f32 = 0.816328;
+
+impl Color {
+    /// bits 0～2 emphasize red, green and blue
+    pub fn emphasize(self, emphasis: u8) -> Self {
+        if emphasis & 0b111 == 0 {
+            return self;
+        }
+        let scale = |v: u8, channel: usize| {
+            let n = (0..3).filter(|&c| c != channel && emphasis.bit(c)).count();
+            (v as f32 * ATTENUATION.powi(n as i32)) as u8
+        };
+        Self {
+            r: scale(self.r, 0),
+            g: scale(self.g, 1),
+            b: scale(self.b, 2),
+        }
+    }
+}
+
 #[rustfmt::skip]
 pub const COLORS: [Color; 64] = [
     Color{r: 0x80, g: 0x80, b: 0x80},
@@ -72,3 +95,72 @@ pub const COLORS: [Color; 64] = [
     Color{r: 0x11, g: 0x11, b: 0x11},
     Color{r: 0x11, g: 0x11, b: 0x11},
 ];
+
+/// 2C07 of PAL, the hues of the 2C02 turned by 15 degrees
+#[rustfmt::skip]
+pub const PAL_COLORS: [Color; 64] = [
+    Color{r: 0x80, g: 0x80, b: 0x80},
+    Color{r: 0x00, g: 0x4A, b: 0x89},
+    Color{r: 0x00, g: 0x20, b: 0x9D},
+    Color{r: 0x33, g: 0x07, b: 0xA0},
+    Color{r: 0x98, g: 0x00, b: 0x8C},
+    Color{r: 0xC6, g: 0x00, b: 0x67},
+    Color{r: 0xBE, g: 0x00, b: 0x3C},
+    Color{r: 0x91, g: 0x0C, b: 0x29},
+    Color{r: 0x63, g: 0x28, b: 0x13},
+    Color{r: 0x18, g: 0x43, b: 0x00},
+    Color{r: 0x0D, g: 0x49, b: 0x00},
+    Color{r: 0x02, g: 0x4A, b: 0x18},
+    Color{r: 0x00, g: 0x49, b: 0x4D},
+    Color{r: 0x00, g: 0x00, b: 0x00},
+    Color{r: 0x05, g: 0x05, b: 0x05},
+    Color{r: 0x05, g: 0x05, b: 0x05},
+    Color{r: 0xC7, g: 0xC7, b: 0xC7},
+    Color{r: 0x00, g: 0x8B, b: 0xCC},
+    Color{r: 0x0B, g: 0x66, b: 0xE0},
+    Color{r: 0x6B, g: 0x41, b: 0xFF},
+    Color{r: 0xDE, g: 0x2C, b: 0xE9},
+    Color{r: 0xFE, g: 0x1C, b: 0x94},
+    Color{r: 0xFF, g: 0x0F, b: 0x4C},
+    Color{r: 0xDF, g: 0x22, b: 0x3B},
+    Color{r: 0xD2, g: 0x53, b: 0x29},
+    Color{r: 0x44, g: 0x7B, b: 0x00},
+    Color{r: 0x14, g: 0x8E, b: 0x00},
+    Color{r: 0x04, g: 0x90, b: 0x2B},
+    Color{r: 0x00, g: 0xA8, b: 0x95},
+    Color{r: 0x21, g: 0x21, b: 0x21},
+    Color{r: 0x09, g: 0x09, b: 0x09},
+    Color{r: 0x09, g: 0x09, b: 0x09},
+    Color{r: 0xFF, g: 0xFF, b: 0xFF},
+    Color{r: 0x06, g: 0xE9, b: 0xB9},
+    Color{r: 0x5C, g: 0xAE, b: 0xE4},
+    Color{r: 0xC6, g: 0x84, b: 0xFF},
+    Color{r: 0xED, g: 0x45, b: 0xFF},
+    Color{r: 0xFD, g: 0x59, b: 0xBC},
+    Color{r: 0xFF, g: 0x78, b: 0x61},
+    Color{r: 0xFF, g: 0x8A, b: 0x3E},
+    Color{r: 0xFF, g: 0xAB, b: 0x41},
+    Color{r: 0xB8, g: 0xD7, b: 0x09},
+    Color{r: 0x3F, g: 0xF0, b: 0x03},
+    Color{r: 0x11, g: 0xFB, b: 0x5E},
+    Color{r: 0x00, g: 0xFF, b: 0xAD},
+    Color{r: 0x5E, g: 0x5E, b: 0x5E},
+    Color{r: 0x0D, g: 0x0D, b: 0x0D},
+    Color{r: 0x0D, g: 0x0D, b: 0x0D},
+    Color{r: 0xFF, g: 0xFF, b: 0xFF},
+    Color{r: 0xA4, g: 0xFF, b: 0xE2},
+    Color{r: 0xB0, g: 0xF2, b: 0xEB},
+    Color{r: 0xD3, g: 0xAD, b: 0xF5},
+    Color{r: 0xF7, g: 0xA8, b: 0xFF},
+    Color{r: 0xFF, g: 0xA5, b: 0xCE},
+    Color{r: 0xFF, g: 0xCC, b: 0xC2},
+    Color{r: 0xFF, g: 0xE8, b: 0xB1},
+    Color{r: 0xFF, g: 0xEF, b: 0xA6},
+    Color{r: 0xE1, g: 0xE3, b: 0x96},
+    Color{r: 0xAC, g: 0xED, b: 0x9C},
+    Color{r: 0xA3, g: 0xF6, b: 0xC1},
+    Color{r: 0x97, g: 0xFF, b: 0xDA},
+    Color{r: 0xDD, g: 0xDD, b: 0xDD},
+    Color{r: 0x11, g: 0x11, b: 0x11},
+    Color{r: 0x11, g: 0x11, b: 0x11},
+];
@@ -1,63 +1,75 @@
-use crate::display::{H, W};
+use crate::display::H;
+use crate::region::Region;
 
-#[derive(Debug, Clone, Copy)]
-pub struct Line(pub usize);
-
-impl Line {
-    pub fn is_last(&self) -> bool {
-        let Line(v) = self;
-        // because line is index of array
-        *v == (H / 8) - 1
-    }
-}
+/// Line: 256 + HBlank = 341
+pub const DOTS: usize = 341;
 
-/// 縦も同様に240列しかないが、20はHBlankが発生する。
-/// この間PPURegisterの値をHBlank中に変更する。
-/// http://pgate1.at-ninja.jp/NES_on_FPGA/nes_ppu.htm
-#[derive(Debug, Default)]
+/// position of the PPU in the frame.
+/// NTSC has 240 visible lines, a post-render line, 20 lines of vblank and the pre-render line.
+/// https://www.nesdev.org/wiki/PPU_rendering
+#[derive(Debug, Default, Clone, Copy)]
 pub struct PPUCycle {
-    cycle: usize,
+    pub frame: usize,
+    pub line: usize,
+    pub dot: usize,
+    pub region: Region,
 }
 
-/// Line: 256 + HBlank = 341
-const HBLANK: usize = 341 - W;
-const H_CYCLE: usize = HBLANK + W;
-
-const VBLANK: usize = 20;
-const V_CYCLE: usize = H + VBLANK;
-
 impl PPUCycle {
-    pub fn add_cycle_with_next(&mut self, cycle: usize) -> Option<Line> {
-        let target_cycles = self.next_target_cycle();
-        self.cycle += cycle;
-        if target_cycles > self.cycle {
-            None
-        } else if self.is_vblank() {
-            None
-        } else {
-            Some(Line(self.cycle_sprite_line(target_cycles)))
-        }
+    pub fn is_visible(&self) -> bool {
+        self.line < H
     }
 
-    fn cycle_sprite_line(&self, cycle: usize) -> usize {
-        cycle / H_CYCLE / 8
+    pub fn is_pre_render(&self) -> bool {
+        self.line == self.region.lines() - 1
     }
 
-    fn next_target_cycle(&self) -> usize {
-        let drawed_sprite_line = self.cycle / H_CYCLE / 8;
-        H_CYCLE * ((drawed_sprite_line + 1) * 8)
+    /// the dot setting the vblank flag
+    pub fn is_vblank_start(&self) -> bool {
+        self.line == self.region.vblank_line() && self.dot == 1
     }
 
-    pub fn is_vblank(&self) -> bool {
-        self.cycle >= H_CYCLE * (V_CYCLE - VBLANK)
+    /// odd frames skip the last dot of the pre-render line when rendering is enabled
+    pub fn is_skipped(&self) -> bool {
+        self.region.skips_dot()
+            && self.is_pre_render()
+            && self.dot == DOTS - 2
+            && self.frame % 2 == 1
     }
 
-    pub fn has_drawed(&self) -> bool {
-        self.cycle > H_CYCLE * V_CYCLE
+    /// moves to the next dot, returns true when the frame is finished
+    pub fn advance(&mut self) -> bool {
+        self.dot += 1;
+        if self.dot < DOTS {
+            return false;
+        }
+        self.dot = 0;
+        self.line += 1;
+        if self.line < self.region.lines() {
+            return false;
+        }
+        self.line = 0;
+        self.frame += 1;
+        true
     }
+}
 
-    pub fn rewind(&mut self) {
-        self.cycle -= H_CYCLE * V_CYCLE;
+#[test]
+fn it_advance() {
+    for (region, lines) in [(Region::NTSC, 262), (Region::PAL, 312)] {
+        let mut cycle = PPUCycle {
+            region,
+            ..Default::default()
+        };
+        for _ in 0..DOTS {
+            assert!(!cycle.advance());
+        }
+        assert_eq!((cycle.line, cycle.dot), (1, 0));
+        for _ in DOTS..DOTS * lines - 1 {
+            assert!(!cycle.advance());
+        }
+        assert!(cycle.is_pre_render());
+        assert!(cycle.advance());
+        assert_eq!((cycle.frame, cycle.line, cycle.dot), (1, 0, 0));
     }
 }
-
@@ -1,30 +1,18 @@
-use super::background_table::BackgroundTable;
 use super::palette::PaletteTable;
+use super::vram::VRAM;
+use crate::cartridge::Cartridge;
 use crate::memory::{RAM, ROM, WOM};
 use crate::result::{e, Result};
 use crate::sprite::Sprite;
-use crate::ines::SpriteROM;
 
 pub struct MemoryMap {
-    // 0x0000～0x0FFF
-    // 0x1000～0x1FFF
-    pub pattern: SpriteROM,
+    // pattern: 0x0000～0x0FFF, 0x1000～0x1FFF, or the VRAM the cartridge maps there
+    // the cartridge may also answer nametable reads
+    pub cartridge: Cartridge,
 
-    /// name0: 0x2000～0x23BF
-    /// attribute0: 0x23C0～0x23FF
-    pub background0: BackgroundTable,
-
-    /// name1 0x2400～0x27BF
-    /// attribute1 0x27C0～0x27FF
-    pub background1: BackgroundTable,
-
-    /// name2 0x2800～0x2BBF
-    /// attribute2 0x2BC0～0x2BFF
-    pub background2: BackgroundTable,
-
-    /// name3 0x2C00～0x2FBF
-    /// attribute3 0x2FC0～0x2FFF
-    pub background3: BackgroundTable,
+    /// name and attribute tables: 0x2000～0x2FFF
+    /// the cartridge selects the page of each nametable
+    pub vram: VRAM,
 
     /// mirror of name and attribute table
     /// 0x3000～0x3EFF
@@ -38,16 +26,38 @@ pub struct MemoryMap {
 }
 
 impl MemoryMap {
-    pub fn sprite(&self, index: usize) -> Sprite {
-        self.pattern[index]
+    /// tile which starts at the address of the pattern table
+    pub fn sprite(&self, addr: usize) -> Sprite {
+        let cartridge = self.cartridge.borrow();
+        match cartridge.character_vram_page(addr) {
+            Some(page) => self.vram.sprite(addr, page),
+            None => cartridge.sprite(addr),
+        }
+    }
+
+    /// tile of objects, some boards switch other banks for them
+    pub fn object_sprite(&self, addr: usize) -> Sprite {
+        let cartridge = self.cartridge.borrow();
+        match cartridge.character_vram_page(addr) {
+            Some(page) => self.vram.sprite(addr, page),
+            None => cartridge.object_sprite(addr),
+        }
+    }
+
+    /// tell the cartridge that the PPU reads the address for rendering
+    pub fn fetch(&self, addr: usize) {
+        self.cartridge.borrow_mut().fetch(addr);
     }
-    pub fn new(pattern: SpriteROM) -> Self {
+
+    /// tell the cartridge that the PPU stopped rendering
+    pub fn idle(&self) {
+        self.cartridge.borrow_mut().idle();
+    }
+
+    pub fn new(cartridge: Cartridge) -> Self {
         MemoryMap {
-            pattern,
-            background0: BackgroundTable::default(),
-            background1: BackgroundTable::default(),
-            background2: BackgroundTable::default(),
-            background3: BackgroundTable::default(),
+            cartridge,
+            vram: VRAM::default(),
             _mirror1: [0; 0],
             palette: PaletteTable::default(),
             _mirror2: [0; 0],
@@ -62,20 +72,24 @@ impl ROM<usize> for MemoryMap {
 
     fn get(&self, i: usize) -> Result<Self::Output> {
         match i {
-            i if (0x0000..=0x1FFF).contains(&i) => unreachable!(), // this is sprite
-            i if (0x2000..=0x23FF).contains(&i) => self.background0.get(i - 0x2000),
-            i if (0x2400..=0x27FF).contains(&i) => self.background1.get(i - 0x2400),
-            i if (0x2800..=0x2BFF).contains(&i) => self.background2.get(i - 0x2800),
-            i if (0x2C00..=0x2FFF).contains(&i) => self.background3.get(i - 0x2C00),
-            i if (0x3000..=0x3EFF).contains(&i) => match i - 0x3000 {
-                i if (0x0000..=0x03FF).contains(&i) => self.background0.get(i),
-                i if (0x0400..=0x07FF).contains(&i) => self.background1.get(i - 0x0400),
-                i if (0x0800..=0x0BFF).contains(&i) => self.background2.get(i - 0x0800),
-                i if (0x0C00..=0x0FFF).contains(&i) => self.background3.get(i - 0x0C00),
-                _ => Err(e::index_out_of_range(i)),
-            },
-            i if (0x3F00..=0x3F1F).contains(&i) => self.palette.get(i - 0x3f00),
-            i if (0x3F20..=0x3FFF).contains(&i) => self.palette.get(i - 0x3f20),
+            i if (0x0000..=0x1FFF).contains(&i) => {
+                let cartridge = self.cartridge.borrow();
+                match cartridge.character_vram_page(i) {
+                    Some(page) => Ok(self.vram.get(i, page)),
+                    None => cartridge.character(i),
+                }
+            }
+            i if (0x2000..=0x2FFF).contains(&i)
+                && self.cartridge.borrow().nametable(i).is_some() =>
+            {
+                Ok(self.cartridge.borrow().nametable(i).unwrap())
+            }
+            i if (0x2000..=0x2FFF).contains(&i) => {
+                let page = self.cartridge.borrow().vram_page(VRAM::slot(i));
+                Ok(self.vram.get(i, page))
+            }
+            i if (0x3000..=0x3EFF).contains(&i) => self.get(i - 0x1000),
+            i if (0x3F00..=0x3FFF).contains(&i) => self.palette.get(i - 0x3F00),
             _ => Err(e::index_out_of_range(i)),
         }
     }
@@ -85,19 +99,73 @@ impl WOM<usize> for MemoryMap {
     type Input = u8;
     fn put(&mut self, i: usize, v: Self::Input) -> Result<()> {
         match i {
-            i if (0x0000..=0x1FFF).contains(&i) => Err(e::readonly(i)),
-            i if (0x2000..=0x23FF).contains(&i) => self.background0.put(i - 0x2000, v),
-            i if (0x2400..=0x27FF).contains(&i) => self.background1.put(i - 0x2400, v),
-            i if (0x2800..=0x2BFF).contains(&i) => self.background2.put(i - 0x2800, v),
-            i if (0x2C00..=0x2FFF).contains(&i) => self.background3.put(i - 0x2C00, v),
-            i if (0x3000..=0x3EFF).contains(&i) => {
-                unreachable!("this is mirror {}", i)
+            i if (0x0000..=0x1FFF).contains(&i) => {
+                let page = self.cartridge.borrow().character_vram_page(i);
+                match page {
+                    Some(page) => {
+                        self.vram.put(i, v, page);
+                        Ok(())
+                    }
+                    None => self.cartridge.borrow_mut().put_character(i, v),
+                }
             }
-            i if (0x3F00..=0x3F1F).contains(&i) => self.palette.put(i - 0x3F00, v),
-            i if (0x3F20..=0x3FFF).contains(&i) => {
-                unreachable!("this is mirror {}", i)
+            i if (0x2000..=0x2FFF).contains(&i)
+                && self.cartridge.borrow_mut().put_nametable(i, v) =>
+            {
+                Ok(())
             }
+            i if (0x2000..=0x2FFF).contains(&i) => {
+                let page = self.cartridge.borrow().vram_page(VRAM::slot(i));
+                self.vram.put(i, v, page);
+                Ok(())
+            }
+            i if (0x3000..=0x3EFF).contains(&i) => self.put(i - 0x1000, v),
+            i if (0x3F00..=0x3FFF).contains(&i) => self.palette.put(i - 0x3F00, v),
             _ => Err(e::index_out_of_range(i)),
         }
     }
 }
+
+#[test]
+fn it_mirroring() {
+    let mut header = crate::ines::INesHeader::new(0x8000, 0x2000);
+    header.set_mapper(21, 0);
+    let mut data = header.to_bytes().unwrap().to_vec();
+    data.resize(16 + 0x8000 + 0x2000, 0);
+    let ines = crate::ines::INes::parse(&data).unwrap();
+    let cartridge = crate::cartridge::load(&ines).unwrap();
+    let mut memory = MemoryMap::new(std::rc::Rc::clone(&cartridge));
+
+    // vertical
+    memory.put(0x2400, 1).unwrap();
+    assert_eq!(memory.get(0x2C00).unwrap(), 1);
+    assert_eq!(memory.get(0x3400).unwrap(), 1);
+    memory.put(0x3805, 2).unwrap();
+    assert_eq!(memory.get(0x2005).unwrap(), 2);
+
+    // VRC4 switches it to horizontal
+    cartridge.borrow_mut().put(0x9000, 1).unwrap();
+    assert_eq!(memory.get(0x2400).unwrap(), 0);
+    assert_eq!(memory.get(0x2405).unwrap(), 2);
+    assert_eq!(memory.get(0x2800).unwrap(), 1);
+}
+
+#[test]
+fn it_character_vram() {
+    let mut header = crate::ines::INesHeader::new(0x8000, 0x2000);
+    header.set_mapper(19, 0);
+    let mut data = header.to_bytes().unwrap().to_vec();
+    data.resize(16 + 0x8000 + 0x2000, 0);
+    let ines = crate::ines::INes::parse(&data).unwrap();
+    let cartridge = crate::cartridge::load(&ines).unwrap();
+    let mut memory = MemoryMap::new(std::rc::Rc::clone(&cartridge));
+
+    // Namco 163 maps the nametable 0x2400 to the pattern table 0x0000
+    cartridge.borrow_mut().put(0x8000, 0xE1).unwrap();
+    memory.put(0x2408, 0xFF).unwrap();
+    assert_eq!(memory.get(0x0008).unwrap(), 0xFF);
+    memory.put(0x0001, 0xFF).unwrap();
+    assert_eq!(memory.get(0x2401).unwrap(), 0xFF);
+    let bits = memory.sprite(0x0000).bits();
+    assert_eq!((bits[0], bits[1]), ([2; 8], [1; 8]));
+}
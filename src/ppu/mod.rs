@@ -1,17 +1,16 @@
-mod attribute_table;
-mod background_table;
-mod buf_byte;
 mod color;
+mod cycle;
 mod memory;
-mod name_table;
+mod oam;
 mod palette;
 mod ppu;
 mod ppu_bus;
 mod register;
-mod cycle;
+mod vram;
 
 pub use color::Color;
+pub use cycle::PPUCycle;
 pub use memory::MemoryMap;
-pub use palette::Palette;
+pub use oam::{Object, OAM};
 pub use ppu::PPU;
 pub use register::Register;
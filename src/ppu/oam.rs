@@ -0,0 +1,208 @@
+use crate::bits::Byte;
+use crate::memory::{RAM, ROM, WOM};
+use crate::result::{e, Result};
+
+pub const OAM_LENGTH: usize = 0x100;
+/// the PPU fetches only 8 objects for a scanline
+pub const OBJECTS_PER_LINE: usize = 8;
+
+/// 4 bytes of OAM
+/// | y | tile | attribute | x |
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Object {
+    /// the object appears from the next scanline of y
+    pub y: u8,
+    pub tile: u8,
+    pub attribute: u8,
+    pub x: u8,
+}
+
+fn row(y: u8, line: usize, height: usize) -> Option<usize> {
+    let row = line.checked_sub(y as usize + 1)?;
+    (row < height).then_some(row)
+}
+
+impl Object {
+    pub fn palette(&self) -> usize {
+        (self.attribute & 0b11) as usize
+    }
+
+    /// drawn only where the background is transparent
+    pub fn behind(&self) -> bool {
+        self.attribute.bit(5)
+    }
+
+    pub fn flip_horizontal(&self) -> bool {
+        self.attribute.bit(6)
+    }
+
+    pub fn flip_vertical(&self) -> bool {
+        self.attribute.bit(7)
+    }
+
+    /// row of the object on the scanline
+    pub fn row(&self, line: usize, height: usize) -> Option<usize> {
+        row(self.y, line, height)
+    }
+
+    /// address of the tile and the row in the tile.
+    /// `table` is used by 8x8 objects, 8x16 objects choose it by bit 0 of the tile.
+    pub fn pattern(&self, row: usize, height: usize, table: usize) -> (usize, usize) {
+        let row = if self.flip_vertical() {
+            height - 1 - row
+        } else {
+            row
+        };
+        if height == 16 {
+            let table = (self.tile & 1) as usize * 0x1000;
+            let tile = (self.tile & 0xFE) as usize + row / 8;
+            (table + tile * 16, row % 8)
+        } else {
+            (table + self.tile as usize * 16, row)
+        }
+    }
+}
+
+/// object attribute memory, 64 objects
+#[derive(Debug)]
+pub struct OAM {
+    raw: [u8; OAM_LENGTH],
+}
+
+impl Default for OAM {
+    fn default() -> Self {
+        Self {
+            raw: [0; OAM_LENGTH],
+        }
+    }
+}
+
+impl OAM {
+    pub fn object(&self, i: usize) -> Object {
+        let raw = &self.raw[i * 4..i * 4 + 4];
+        Object {
+            y: raw[0],
+            tile: raw[1],
+            attribute: raw[2],
+            x: raw[3],
+        }
+    }
+
+    /// objects on the scanline with their index in OAM order, the rest of them are dropped
+    pub fn evaluate(&self, line: usize, height: usize) -> Vec<(usize, Object)> {
+        (0..OAM_LENGTH / 4)
+            .map(|i| (i, self.object(i)))
+            .filter(|(_, object)| object.row(line, height).is_some())
+            .take(OBJECTS_PER_LINE)
+            .collect()
+    }
+
+    /// sprite overflow of the scanline as the PPU evaluates it.
+    /// after 8 objects are found, a bug of the PPU increments the byte index with the object index,
+    /// so a tile, an attribute or x is taken as y.
+    pub fn overflow(&self, line: usize, height: usize) -> bool {
+        let objects = OAM_LENGTH / 4;
+        let mut n = 0;
+        let mut found = 0;
+        while n < objects && found < OBJECTS_PER_LINE {
+            if row(self.raw[n * 4], line, height).is_some() {
+                found += 1;
+            }
+            n += 1;
+        }
+        let mut m = 0;
+        while n < objects {
+            if row(self.raw[n * 4 + m], line, height).is_some() {
+                return true;
+            }
+            n += 1;
+            m = (m + 1) % 4;
+        }
+        false
+    }
+}
+
+impl RAM<usize> for OAM {}
+
+impl ROM<usize> for OAM {
+    type Output = u8;
+
+    fn get(&self, i: usize) -> Result<Self::Output> {
+        if i < OAM_LENGTH {
+            Ok(self.raw[i])
+        } else {
+            Err(e::index_out_of_range(i))
+        }
+    }
+}
+
+impl WOM<usize> for OAM {
+    type Input = u8;
+
+    fn put(&mut self, i: usize, v: Self::Input) -> Result<()> {
+        if i < OAM_LENGTH {
+            // bits 2～4 of the attribute do not exist
+            self.raw[i] = if i % 4 == 2 { v & 0xE3 } else { v };
+            Ok(())
+        } else {
+            Err(e::index_out_of_range(i))
+        }
+    }
+}
+
+#[test]
+fn it_evaluate() {
+    let mut oam = OAM::default();
+    // 9 objects on the line 11, the one at y = 20 is not
+    for i in 0..10 {
+        oam.put(i * 4, if i == 4 { 20 } else { 10 }).unwrap();
+        oam.put(i * 4 + 1, i as u8).unwrap();
+    }
+    for i in 10..64 {
+        oam.put(i * 4, 0xFF).unwrap();
+    }
+    let objects = oam.evaluate(11, 8);
+    assert_eq!(objects.len(), OBJECTS_PER_LINE);
+    assert_eq!(
+        objects.iter().map(|(_, o)| o.tile).collect::<Vec<_>>(),
+        vec![0, 1, 2, 3, 5, 6, 7, 8]
+    );
+    assert!(oam.evaluate(10, 8).is_empty());
+    assert_eq!(oam.evaluate(28, 16).len(), 1);
+}
+
+#[test]
+fn it_overflow() {
+    let mut oam = OAM::default();
+    for i in 0..64 {
+        oam.put(i * 4, 0xFF).unwrap();
+    }
+    // 8 objects on the line 11
+    for i in 0..8 {
+        oam.put(i * 4, 10).unwrap();
+    }
+    assert!(!oam.overflow(11, 8));
+    // the 9th object is checked by its y
+    oam.put(8 * 4, 10).unwrap();
+    assert!(oam.overflow(11, 8));
+    oam.put(8 * 4, 0xFF).unwrap();
+    // the 10th object is checked by its tile, its y is missed
+    oam.put(9 * 4, 10).unwrap();
+    assert!(!oam.overflow(11, 8));
+    oam.put(9 * 4 + 1, 10).unwrap();
+    assert!(oam.overflow(11, 8));
+}
+
+#[test]
+fn it_pattern() {
+    let object = Object {
+        y: 0,
+        tile: 0x13,
+        attribute: 0x80,
+        x: 0,
+    };
+    assert_eq!(object.pattern(0, 8, 0x1000), (0x1130, 7));
+    // the bottom tile comes first when flipped
+    assert_eq!(object.pattern(2, 16, 0x0000), (0x1130, 5));
+    assert_eq!(object.pattern(9, 16, 0x0000), (0x1120, 6));
+}
@@ -1,10 +1,9 @@
-use super::color::{Color, COLORS};
+use super::color::{Color, COLORS, PAL_COLORS};
+use crate::bits::Byte;
 use crate::memory::{RAM, ROM, WOM};
+use crate::region::Region;
 use crate::result::{e, Result};
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
-pub struct Palette(pub Color, pub Color, pub Color, pub Color);
-
 #[derive(Default, Debug)]
 pub struct PaletteTable {
     raw: [u8; 0x20],
@@ -12,13 +11,17 @@ pub struct PaletteTable {
 
 impl RAM<usize> for PaletteTable {}
 
+/// 0x3F00～0x3FFF
+const MIRRORED_LENGTH: usize = 0x100;
+
+/// 0x00～0xFF of 0x3F00～0x3FFF, entries are 6 bit.
 impl WOM<usize> for PaletteTable {
     type Input = u8;
     fn put(&mut self, i: usize, v: Self::Input) -> Result<()> {
-        if self.raw.len() < i {
+        if i >= MIRRORED_LENGTH {
             Err(e::index_out_of_range(i))
         } else {
-            self.raw[i] = v;
+            self.raw[Self::index(i)] = v & 0x3F;
             Ok(())
         }
     }
@@ -28,91 +31,80 @@ impl ROM<usize> for PaletteTable {
     type Output = u8;
 
     fn get(&self, i: usize) -> Result<Self::Output> {
-        if self.raw.len() < i {
+        if i >= MIRRORED_LENGTH {
             Err(e::index_out_of_range(i))
         } else {
-            Ok(self.raw[i])
+            Ok(self.raw[Self::index(i)])
         }
     }
 }
 
 impl PaletteTable {
-    pub fn background_color(&self) -> Color {
-        COLORS[self.raw[0x00] as usize]
+    /// the memory repeats every 0x20 bytes,
+    /// and the first colors of the object palettes are the ones of the background palettes.
+    fn index(i: usize) -> usize {
+        let i = i % 0x20;
+        if i >= 0x10 && i.is_multiple_of(4) {
+            i - 0x10
+        } else {
+            i
+        }
     }
 
-    pub fn background_palette(&self, i: usize) -> Palette {
-        match i {
-            0 => self.background_palette0(),
-            1 => self.background_palette1(),
-            2 => self.background_palette2(),
-            3 => self.background_palette3(),
-            _ => unreachable!(),
+    /// color of the entry as the PPU of the region outputs it.
+    /// PPUMASK bit 0 takes the grey column, bits 5～7 emphasize red, green and blue.
+    pub fn color(&self, i: usize, mask: u8, region: Region) -> Color {
+        let mut v = self.raw[Self::index(i)];
+        if mask.bit(0) {
+            v &= 0x30;
         }
+        let colors = match region {
+            Region::NTSC => &COLORS,
+            Region::PAL | Region::Dendy => &PAL_COLORS,
+        };
+        colors[v as usize].emphasize(region.mask(mask) >> 5)
     }
-    fn background_palette0(&self) -> Palette {
-        Palette(
-            self.background_color(),
-            COLORS[self.raw[0x01] as usize],
-            COLORS[self.raw[0x02] as usize],
-            COLORS[self.raw[0x03] as usize],
-        )
-    }
-    fn background_palette1(&self) -> Palette {
-        Palette(
-            self.background_color(),
-            COLORS[self.raw[0x05] as usize],
-            COLORS[self.raw[0x06] as usize],
-            COLORS[self.raw[0x07] as usize],
-        )
-    }
-    fn background_palette2(&self) -> Palette {
-        Palette(
-            self.background_color(),
-            COLORS[self.raw[0x09] as usize],
-            COLORS[self.raw[0x0A] as usize],
-            COLORS[self.raw[0x0B] as usize],
-        )
-    }
-    fn background_palette3(&self) -> Palette {
-        Palette(
-            self.background_color(),
-            COLORS[self.raw[0x0D] as usize],
-            COLORS[self.raw[0x0E] as usize],
-            COLORS[self.raw[0x0F] as usize],
-        )
-    }
+}
 
-    pub fn sprite_palette0(&self) -> Palette {
-        Palette(
-            COLORS[self.raw[0x11] as usize],
-            COLORS[self.raw[0x12] as usize],
-            COLORS[self.raw[0x13] as usize],
-            COLORS[self.raw[0x04] as usize], // mirror
-        )
-    }
-    pub fn sprite_palette1(&self) -> Palette {
-        Palette(
-            COLORS[self.raw[0x15] as usize],
-            COLORS[self.raw[0x16] as usize],
-            COLORS[self.raw[0x17] as usize],
-            COLORS[self.raw[0x08] as usize], // mirror
-        )
-    }
-    pub fn sprite_palette2(&self) -> Palette {
-        Palette(
-            COLORS[self.raw[0x19] as usize],
-            COLORS[self.raw[0x1A] as usize],
-            COLORS[self.raw[0x1B] as usize],
-            COLORS[self.raw[0x0C] as usize], // mirror
-        )
-    }
-    pub fn sprite_palette3(&self) -> Palette {
-        Palette(
-            COLORS[self.raw[0x1D] as usize],
-            COLORS[self.raw[0x1E] as usize],
-            COLORS[self.raw[0x1F] as usize],
-            self.background_color(), // TODO: よくわかってない
-        )
-    }
+#[test]
+fn it_mirror() {
+    let mut palette = PaletteTable::default();
+    palette.put(0x10, 0x21).unwrap();
+    assert_eq!(palette.get(0x00).unwrap(), 0x21);
+    palette.put(0x04, 0x22).unwrap();
+    assert_eq!(palette.get(0x14).unwrap(), 0x22);
+    // 6 bit
+    palette.put(0x20, 0xFF).unwrap();
+    assert_eq!(palette.get(0x00).unwrap(), 0x3F);
+    palette.put(0x11, 0x05).unwrap();
+    assert_eq!(palette.get(0xF1).unwrap(), 0x05);
+    assert_eq!(palette.get(0x01).unwrap(), 0x00);
+    assert!(palette.get(0x100).is_err());
+}
+
+#[test]
+fn it_color() {
+    let mut palette = PaletteTable::default();
+    palette.put(0x01, 0x16).unwrap();
+    assert_eq!(palette.color(0x01, 0, Region::NTSC), COLORS[0x16]);
+    assert_eq!(palette.color(0x01, 0b1, Region::NTSC), COLORS[0x10]);
+    // red is emphasized, green and blue are darker
+    let color = palette.color(0x01, 0b0010_0000, Region::NTSC);
+    assert_eq!(color.r, COLORS[0x16].r);
+    assert!(color.g < COLORS[0x16].g);
+}
+
+#[test]
+fn it_pal_color() {
+    let mut palette = PaletteTable::default();
+    palette.put(0x01, 0x16).unwrap();
+    assert_eq!(palette.color(0x01, 0, Region::PAL), PAL_COLORS[0x16]);
+    assert_ne!(palette.color(0x01, 0, Region::PAL), COLORS[0x16]);
+    // the grey column is the same
+    palette.put(0x02, 0x30).unwrap();
+    assert_eq!(palette.color(0x02, 0, Region::PAL), COLORS[0x30]);
+    // PAL emphasizes green by bit 5
+    let color = palette.color(0x01, 0b0010_0000, Region::PAL);
+    assert_eq!(color.g, PAL_COLORS[0x16].g);
+    assert!(color.r < PAL_COLORS[0x16].r);
 }
@@ -1,17 +1,45 @@
-use super::cycle::{Line, PPUCycle};
+use super::cycle::PPUCycle;
 use super::memory::MemoryMap;
+use super::oam::{Object, OAM, OBJECTS_PER_LINE};
 use super::register::Register;
-use crate::display::Display;
+use crate::bits::Byte;
+use crate::display::{Display, W};
 use crate::memory::{RAM, ROM};
+use crate::region::Region;
 use crate::result::Result;
-use crate::vec2::Vec2;
 use std::cell::RefCell;
 use std::rc::Rc;
 
+/// tile of the background being fetched, it goes to the shift registers every 8 dots
+#[derive(Debug, Default, Clone, Copy)]
+struct Tile {
+    name: u8,
+    attribute: u8,
+    low: u8,
+    high: u8,
+}
+
+/// object of the scanline with the pixels of its row
+#[derive(Debug, Clone, Copy)]
+struct LineObject {
+    index: usize,
+    object: Object,
+    pixels: [u8; 8],
+}
+
 pub struct PPU {
     cycle: PPUCycle,
     register: RefCell<Register>,
     memory: MemoryMap,
+    oam: OAM,
+    tile: Tile,
+    /// shift registers of the background, the upper bits are drawn
+    pattern_low: u16,
+    pattern_high: u16,
+    attribute_low: u16,
+    attribute_high: u16,
+    /// objects of the scanline, fetched on the previous scanline
+    objects: Vec<LineObject>,
     display: Rc<RefCell<Display>>,
 }
 
@@ -25,6 +53,13 @@ impl PPU {
             cycle: PPUCycle::default(),
             register,
             memory,
+            oam: OAM::default(),
+            tile: Tile::default(),
+            pattern_low: 0,
+            pattern_high: 0,
+            attribute_low: 0,
+            attribute_high: 0,
+            objects: vec![],
             display,
         }
     }
@@ -43,66 +78,288 @@ impl PPU {
         fun(&mut self.register.borrow_mut(), &mut self.memory)
     }
 
-    /// 縦も同様に240列しかないが、20はHBlankが発生する。
-    /// この間PPURegisterの値をHBlank中に変更する。
-    /// http://pgate1.at-ninja.jp/NES_on_FPGA/nes_ppu.htm
+    pub fn handle_oam<R, Fn>(&mut self, mut fun: Fn) -> Result<R>
+    where
+        Fn: FnMut(&mut Register, &mut OAM) -> Result<R>,
+    {
+        fun(&mut self.register.borrow_mut(), &mut self.oam)
+    }
+
+    /// position of the next dot
+    pub fn cycle(&self) -> PPUCycle {
+        self.cycle
+    }
+
+    pub fn oam(&self) -> &OAM {
+        &self.oam
+    }
+
+    /// NMI raised since the last call
+    pub fn nmi(&self) -> bool {
+        self.register.borrow_mut().take_nmi()
+    }
+
+    /// timing of the frame, NTSC by default
+    pub fn with_region(mut self, region: Region) -> Self {
+        self.cycle.region = region;
+        self
+    }
+
+    pub fn region(&self) -> Region {
+        self.cycle.region
+    }
+
+    /// runs the dots and returns true when a frame is finished.
+    /// a frame is 262 lines (312 on PAL and Dendy) of 341 dots, the first 240 lines are drawn.
+    /// https://www.nesdev.org/wiki/PPU_rendering
     pub fn exec(&mut self, cycle: usize) -> Result<bool> {
-        if let Some(line) = self.cycle.add_cycle_with_next(cycle) {
-            let Line(y) = line;
-            let v = self.register.borrow().scroll_offset.older() as usize;
-            let h = self.register.borrow().scroll_offset.later() as usize;
-            let (name, attribute) = self.fetch_background_line(Vec2::new(v, h + y));
-            for x in 0..32 {
-                let sprite = self.memory.sprite(name[x] as usize);
-                if !sprite.zero() {
-                    let palette = &self
-                        .memory
-                        .palette
-                        .background_palette(attribute[x / 2] as usize);
-                    self.display.borrow_mut().put_image(x, y, &sprite, &palette);
+        let mut drawed = false;
+        for _ in 0..cycle {
+            self.step()?;
+            if self.cycle.is_skipped() && self.rendering() {
+                self.cycle.advance();
+            }
+            drawed |= self.cycle.advance();
+        }
+        Ok(drawed)
+    }
+
+    fn step(&mut self) -> Result<()> {
+        let PPUCycle { line, dot, .. } = self.cycle;
+        let fetching = self.rendering() && (self.cycle.is_visible() || self.cycle.is_pre_render());
+        if fetching {
+            self.fetch_background()?;
+        }
+        match dot {
+            // objects of the next line are evaluated on dots 65～256
+            65 if fetching
+                && self.cycle.is_visible()
+                && self.oam.overflow(line + 1, self.object_height()) =>
+            {
+                self.register.borrow_mut().toggle_sprite_overflow(true);
+            }
+            257 if fetching => {
+                let next = if self.cycle.is_pre_render() {
+                    0
                 } else {
-                    self.display.borrow_mut().put_plane(
-                        x,
-                        y,
-                        &self.memory.palette.background_color(),
-                    );
-                }
+                    line + 1
+                };
+                self.objects = self.fetch_objects(next);
             }
+            257 => self.objects.clear(),
+            _ => {}
+        }
+
+        if self.cycle.is_visible() && (1..=W).contains(&dot) {
+            self.draw_pixel(dot - 1, line);
+        }
+
+        if self.cycle.is_vblank_start() {
+            self.register.borrow_mut().toggle_hbrank(true);
+            self.memory.idle();
+        } else if dot == 1 && self.cycle.is_pre_render() {
+            let mut register = self.register.borrow_mut();
+            register.toggle_hbrank(false);
+            register.toggle_sprite_zero_hit(false);
+            register.toggle_sprite_overflow(false);
+        }
+        Ok(())
+    }
 
-            if line.is_last() {
-                self.register.borrow_mut().toggle_hbrank(true);
+    /// fetches of the background on the dot.
+    /// a tile takes 8 dots, the first two tiles of a line are fetched at the end of the previous line.
+    fn fetch_background(&mut self) -> Result<()> {
+        let dot = self.cycle.dot;
+        if matches!(dot, 2..=257 | 322..=337) {
+            self.shift();
+        }
+        if matches!(dot, 9..=257 | 329..=337) && (dot - 1).is_multiple_of(8) {
+            self.reload();
+        }
+        if matches!(dot, 1..=256 | 321..=336) {
+            match (dot - 1) % 8 {
+                0 => self.tile.name = self.fetch_name()?,
+                2 => self.tile.attribute = self.fetch_attribute()?,
+                4 => {
+                    let (addr, fine_y) = self.pattern_addr();
+                    self.memory.fetch(addr + fine_y);
+                    let pixels = self.memory.sprite(addr).bits()[fine_y];
+                    self.tile.low = pixels.iter().fold(0, |v, p| v << 1 | (p & 1));
+                    self.tile.high = pixels.iter().fold(0, |v, p| v << 1 | (p >> 1));
+                }
+                6 => {
+                    let (addr, fine_y) = self.pattern_addr();
+                    self.memory.fetch(addr + 8 + fine_y);
+                }
+                7 => self.register.borrow_mut().increment_x(),
+                _ => {}
+            }
+        }
+        match dot {
+            256 => self.register.borrow_mut().increment_y(),
+            257 => self.register.borrow_mut().copy_x(),
+            // dummy fetches, the cartridge may count them
+            337 | 339 => {
+                self.fetch_name()?;
             }
+            280..=304 if self.cycle.is_pre_render() => self.register.borrow_mut().copy_y(),
+            _ => {}
         }
+        Ok(())
+    }
+
+    fn fetch_name(&self) -> Result<u8> {
+        let addr = self.register.borrow().name_addr();
+        self.memory.fetch(addr);
+        self.memory.get(addr)
+    }
+
+    /// palette index of the tile
+    fn fetch_attribute(&self) -> Result<u8> {
+        let (addr, shift) = self.register.borrow().attribute_addr();
+        self.memory.fetch(addr);
+        Ok((self.memory.get(addr)? >> shift) & 0b11)
+    }
+
+    /// address of the fetched tile and the row in it
+    fn pattern_addr(&self) -> (usize, usize) {
+        let register = self.register.borrow();
+        let table = if register.control1.bit(4) {
+            0x1000
+        } else {
+            0x0000
+        };
+        (table + self.tile.name as usize * 16, register.fine_y())
+    }
 
-        if self.cycle.has_drawed() {
-            self.cycle.rewind();
-            // TODO: 無理やりすぎる
-            return Ok(true);
+    fn shift(&mut self) {
+        self.pattern_low <<= 1;
+        self.pattern_high <<= 1;
+        self.attribute_low <<= 1;
+        self.attribute_high <<= 1;
+    }
+
+    /// the fetched tile goes to the lower bits
+    fn reload(&mut self) {
+        let fill = |v: bool| if v { 0xFF } else { 0x00 };
+        self.pattern_low = (self.pattern_low & 0xFF00) | self.tile.low as u16;
+        self.pattern_high = (self.pattern_high & 0xFF00) | self.tile.high as u16;
+        self.attribute_low = (self.attribute_low & 0xFF00) | fill(self.tile.attribute.bit(0));
+        self.attribute_high = (self.attribute_high & 0xFF00) | fill(self.tile.attribute.bit(1));
+    }
+
+    /// color and palette index of the background at the dot
+    fn background_pixel(&self) -> (u8, usize) {
+        let bit = 15 - self.register.borrow().fine_x() as usize;
+        let pixel = ((self.pattern_high >> bit) & 1) << 1 | ((self.pattern_low >> bit) & 1);
+        let palette = ((self.attribute_high >> bit) & 1) << 1 | ((self.attribute_low >> bit) & 1);
+        (pixel as u8, palette as usize)
+    }
+
+    /// the first opaque object in OAM order takes the pixel even if it is behind the background.
+    /// with rendering disabled, the backdrop is drawn, or the palette entry v points to.
+    fn draw_pixel(&mut self, x: usize, line: usize) {
+        let mask = self.register.borrow().control2;
+        let entry = if !self.rendering() {
+            let addr = self.register.borrow().ppu_addr();
+            if addr >= 0x3F00 {
+                addr as usize & 0x1F
+            } else {
+                0
+            }
+        } else {
+            // PPUMASK hides each layer, or its left 8 pixels
+            let (pixel, attribute) = if mask.bit(3) && (x >= 8 || mask.bit(1)) {
+                self.background_pixel()
+            } else {
+                (0, 0)
+            };
+            let object = self
+                .objects
+                .iter()
+                .filter(|_| mask.bit(4) && (x >= 8 || mask.bit(2)))
+                .find_map(|o| {
+                    let i = x.checked_sub(o.object.x as usize)?;
+                    let v = *o.pixels.get(i)?;
+                    (v != 0).then_some((o, v))
+                });
+            if let Some((o, _)) = object {
+                if o.index == 0 && pixel != 0 && self.sprite_zero_hits(x) {
+                    self.register.borrow_mut().toggle_sprite_zero_hit(true);
+                }
+            }
+            // entry of the palette memory
+            match object {
+                Some((o, v)) if !o.object.behind() || pixel == 0 => {
+                    0x10 + o.object.palette() * 4 + v as usize
+                }
+                _ if pixel != 0 => attribute * 4 + pixel as usize,
+                _ => 0,
+            }
+        };
+        let color = self.memory.palette.color(entry, mask, self.cycle.region);
+        self.display.borrow_mut().put_pixel(x, line, &color);
+    }
+
+    /// PPUMASK shows the background or objects
+    fn rendering(&self) -> bool {
+        let mask = self.register.borrow().control2;
+        mask.bit(3) || mask.bit(4)
+    }
+
+    fn object_height(&self) -> usize {
+        if self.register.borrow().control1.bit(5) {
+            16
+        } else {
+            8
         }
-        Ok(false)
-    }
-
-    /// | 0 | 1 |
-    /// | 2 | 3 |
-    fn fetch_background_line(&self, pos: Vec2<usize>) -> ([u8; 32], [u8; 16]) {
-        let (x, y) = pos.xy();
-        if y < 30 {
-            let (name0, attribute0) = self.memory.background0.fetch_line(pos, 32 - x);
-            let (name1, attribute1) = self.memory.background1.fetch_line(Vec2::new(0, y), x);
-            (
-                [name0, name1].concat().try_into().unwrap(),
-                [attribute0, attribute1].concat().try_into().unwrap(),
-            )
+    }
+
+    /// sprite 0 hits the opaque background at x.
+    /// it needs both layers, and misses x = 255 and the left 8 pixels clipped by PPUMASK.
+    fn sprite_zero_hits(&self, x: usize) -> bool {
+        let mask = self.register.borrow().control2;
+        let clipped = x < 8 && !(mask.bit(1) && mask.bit(2));
+        mask.bit(3) && mask.bit(4) && x != 255 && !clipped
+    }
+
+    /// objects of the line, their patterns are fetched on dots 257～320
+    fn fetch_objects(&self, line: usize) -> Vec<LineObject> {
+        let height = self.object_height();
+        let table = if self.register.borrow().control1.bit(3) {
+            0x1000
         } else {
-            let pos = pos - Vec2::new(0, 30);
-            let (x, y) = pos.xy();
-            let (name2, attribute2) = self.memory.background2.fetch_line(pos, 32 - x);
-            let (name3, attribute3) = self.memory.background3.fetch_line(Vec2::new(0, y), x);
-            (
-                [name2, name3].concat().try_into().unwrap(),
-                [attribute2, attribute3].concat().try_into().unwrap(),
-            )
+            0x0000
+        };
+
+        let objects = self
+            .oam
+            .evaluate(line, height)
+            .into_iter()
+            .filter_map(|(index, object)| {
+                let row = object.row(line, height)?;
+                let (addr, fine_y) = object.pattern(row, height, table);
+                self.memory.fetch(addr + fine_y);
+                self.memory.fetch(addr + 8 + fine_y);
+                let mut pixels = self.memory.object_sprite(addr).bits()[fine_y];
+                if object.flip_horizontal() {
+                    pixels.reverse();
+                }
+                Some(LineObject {
+                    index,
+                    object,
+                    pixels,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // empty slots fetch the tile 0xFF
+        let dummy = if height == 16 { 0x1FF0 } else { table + 0x0FF0 };
+        for _ in objects.len()..OBJECTS_PER_LINE {
+            self.memory.fetch(dummy);
+            self.memory.fetch(dummy + 8);
         }
+        objects
     }
 }
 
@@ -111,3 +368,322 @@ impl std::fmt::Display for PPU {
         write!(f, "{}", self.register.borrow())
     }
 }
+
+#[cfg(test)]
+use super::cycle::DOTS;
+
+/// PPU with an NROM cartridge of the pattern table
+#[cfg(test)]
+pub fn mock(character: &[u8]) -> PPU {
+    let mut data = crate::ines::INesHeader::new(0x4000, 0x2000)
+        .to_bytes()
+        .unwrap()
+        .to_vec();
+    data.resize(16 + 0x4000, 0);
+    data.extend_from_slice(character);
+    data.resize(16 + 0x4000 + 0x2000, 0);
+    let ines = crate::ines::INes::parse(&data).unwrap();
+    let cartridge = crate::cartridge::load(&ines).unwrap();
+    PPU::new(
+        RefCell::new(Register::default()),
+        MemoryMap::new(cartridge),
+        Rc::new(RefCell::new(Display::default())),
+    )
+}
+
+#[cfg(test)]
+fn mock_frame(ppu: &mut PPU) {
+    while !ppu.exec(341).unwrap() {}
+}
+
+#[cfg(test)]
+fn mock_pixel(ppu: &PPU, x: usize, y: usize) -> super::Color {
+    let [r, g, b, _] = ppu.display.borrow().image.get_image_data()[y * W + x];
+    super::Color { r, g, b }
+}
+
+#[test]
+fn it_draw_objects() {
+    use super::color::COLORS;
+    use crate::memory::WOM;
+
+    // tile 1 is filled with the color 1, tile 2 with the color 3
+    let mut character = vec![0; 0x30];
+    character[0x10..0x18].fill(0xFF);
+    character[0x20..0x30].fill(0xFF);
+    let mut ppu = mock(&character);
+    ppu.handle_mut(|_, memory| {
+        memory.put(0x3F00, 0x0F)?;
+        memory.put(0x3F03, 0x30)?;
+        memory.put(0x3F11, 0x16)?;
+        memory.put(0x3F15, 0x2A)?;
+        // the first tile of the third row is opaque
+        memory.put(0x2040, 2)
+    })
+    .unwrap();
+    // | y | tile | attribute | x |
+    ppu.put(1, 0x1E).unwrap();
+    mock_objects(
+        &mut ppu,
+        &[
+            [15, 1, 0x00, 16],
+            // behind the background
+            [15, 1, 0x20, 4],
+            // under the first object
+            [15, 1, 0x01, 20],
+        ],
+    );
+    mock_frame(&mut ppu);
+
+    // objects appear from the next scanline of y
+    assert_eq!(mock_pixel(&ppu, 16, 15), COLORS[0x0F]);
+    assert_eq!(mock_pixel(&ppu, 16, 16), COLORS[0x16]);
+    assert_eq!(mock_pixel(&ppu, 23, 23), COLORS[0x16]);
+    assert_eq!(mock_pixel(&ppu, 16, 24), COLORS[0x0F]);
+    // the lower index wins, the next one shows where the first one ends
+    assert_eq!(mock_pixel(&ppu, 27, 16), COLORS[0x2A]);
+    assert_eq!(mock_pixel(&ppu, 5, 16), COLORS[0x30]);
+    assert_eq!(mock_pixel(&ppu, 8, 16), COLORS[0x16]);
+}
+
+#[cfg(test)]
+fn mock_objects(ppu: &mut PPU, objects: &[[u8; 4]]) {
+    use crate::memory::WOM;
+    ppu.put(3, 0).unwrap();
+    for v in objects.iter().flatten() {
+        ppu.put(4, *v).unwrap();
+    }
+    for _ in objects.len() * 4..0x100 {
+        ppu.put(4, 0xFF).unwrap();
+    }
+}
+
+#[test]
+fn it_sprite_zero_hit() {
+    use crate::memory::WOM;
+
+    let mut character = vec![0; 0x20];
+    character[0x10..0x18].fill(0xFF);
+    let mut ppu = mock(&character);
+    ppu.handle_mut(|_, memory| memory.put(0x2042, 1)).unwrap();
+    ppu.put(1, 0x1E).unwrap();
+    mock_objects(&mut ppu, &[[15, 1, 0x20, 20]]);
+    let status = |ppu: &PPU| ppu.handle(|register, _| Ok(register.status)).unwrap();
+
+    // the pixel x = 20 is drawn on the dot 21
+    ppu.exec(16 * DOTS + 21).unwrap();
+    assert!(!status(&ppu).bit(6));
+    ppu.exec(1).unwrap();
+    assert!(status(&ppu).bit(6));
+    // cleared at the pre-render line
+    mock_frame(&mut ppu);
+    assert!(!status(&ppu).bit(6));
+
+    assert!(ppu.sprite_zero_hits(0));
+    assert!(!ppu.sprite_zero_hits(255));
+    ppu.put(1, 0x1A).unwrap();
+    assert!(!ppu.sprite_zero_hits(7));
+    assert!(ppu.sprite_zero_hits(8));
+    ppu.put(1, 0x0E).unwrap();
+    assert!(!ppu.sprite_zero_hits(8));
+}
+
+#[test]
+fn it_sprite_overflow() {
+    use crate::memory::WOM;
+
+    let mut ppu = mock(&[]);
+    ppu.put(1, 0x1E).unwrap();
+    mock_objects(&mut ppu, &[[15, 0, 0, 0]; 9]);
+    let status = |ppu: &PPU| ppu.handle(|register, _| Ok(register.status)).unwrap();
+
+    ppu.exec(15 * DOTS + 65).unwrap();
+    assert!(!status(&ppu).bit(5));
+    ppu.exec(1).unwrap();
+    assert!(status(&ppu).bit(5));
+    mock_frame(&mut ppu);
+    assert!(!status(&ppu).bit(5));
+}
+
+#[test]
+fn it_scroll() {
+    use super::color::COLORS;
+    use crate::memory::WOM;
+
+    let mut character = vec![0; 0x20];
+    character[0x10..0x18].fill(0xFF);
+    let mut ppu = mock(&character);
+    ppu.handle_mut(|_, memory| {
+        memory.put(0x3F00, 0x0F)?;
+        memory.put(0x3F01, 0x16)?;
+        memory.put(0x2042, 1)?;
+        memory.put(0x21A2, 1)
+    })
+    .unwrap();
+    ppu.put(1, 0x0A).unwrap();
+    ppu.put(5, 3).unwrap();
+    ppu.put(5, 2).unwrap();
+    // the vertical scroll is taken at the pre-render line
+    mock_frame(&mut ppu);
+    mock_frame(&mut ppu);
+    assert_eq!(mock_pixel(&ppu, 12, 14), COLORS[0x0F]);
+    assert_eq!(mock_pixel(&ppu, 13, 14), COLORS[0x16]);
+    assert_eq!(mock_pixel(&ppu, 20, 21), COLORS[0x16]);
+    assert_eq!(mock_pixel(&ppu, 21, 21), COLORS[0x0F]);
+    assert_eq!(mock_pixel(&ppu, 20, 22), COLORS[0x0F]);
+
+    // the horizontal scroll changes in the middle of the frame
+    ppu.exec(100 * DOTS).unwrap();
+    ppu.put(5, 0).unwrap();
+    ppu.put(5, 0).unwrap();
+    mock_frame(&mut ppu);
+    assert_eq!(mock_pixel(&ppu, 13, 14), COLORS[0x16]);
+    assert_eq!(mock_pixel(&ppu, 15, 105), COLORS[0x0F]);
+    assert_eq!(mock_pixel(&ppu, 16, 105), COLORS[0x16]);
+}
+
+#[test]
+fn it_mask() {
+    use super::color::COLORS;
+    use crate::memory::WOM;
+
+    let mut character = vec![0; 0x20];
+    character[0x10..0x20].fill(0xFF);
+    let mut ppu = mock(&character);
+    ppu.handle_mut(|_, memory| {
+        memory.put(0x3F00, 0x0F)?;
+        memory.put(0x3F03, 0x16)?;
+        memory.put(0x3F13, 0x2A)?;
+        memory.put(0x2040, 1)?;
+        memory.put(0x2041, 1)
+    })
+    .unwrap();
+    mock_objects(&mut ppu, &[[15, 1, 0x00, 12]]);
+
+    // the left 8 pixels are clipped
+    ppu.put(1, 0x18).unwrap();
+    mock_frame(&mut ppu);
+    assert_eq!(mock_pixel(&ppu, 7, 16), COLORS[0x0F]);
+    assert_eq!(mock_pixel(&ppu, 8, 16), COLORS[0x16]);
+    assert_eq!(mock_pixel(&ppu, 12, 16), COLORS[0x2A]);
+    // only the background
+    ppu.put(1, 0x0A).unwrap();
+    mock_frame(&mut ppu);
+    assert_eq!(mock_pixel(&ppu, 7, 16), COLORS[0x16]);
+    assert_eq!(mock_pixel(&ppu, 12, 16), COLORS[0x16]);
+    // only the objects
+    ppu.put(1, 0x14).unwrap();
+    mock_frame(&mut ppu);
+    assert_eq!(mock_pixel(&ppu, 8, 16), COLORS[0x0F]);
+    assert_eq!(mock_pixel(&ppu, 12, 16), COLORS[0x2A]);
+
+    // forced blank draws the backdrop, or the palette entry v points to
+    ppu.put(1, 0x00).unwrap();
+    ppu.put(6, 0x20).unwrap();
+    ppu.put(6, 0x00).unwrap();
+    mock_frame(&mut ppu);
+    assert_eq!(mock_pixel(&ppu, 12, 16), COLORS[0x0F]);
+    ppu.put(6, 0x3F).unwrap();
+    ppu.put(6, 0x13).unwrap();
+    mock_frame(&mut ppu);
+    assert_eq!(mock_pixel(&ppu, 12, 16), COLORS[0x2A]);
+    // uploads keep v untouched by the rendering
+    let addr = ppu.handle(|register, _| Ok(register.ppu_addr())).unwrap();
+    assert_eq!(addr, 0x3F13);
+}
+
+#[test]
+fn it_vblank() {
+    use crate::memory::WOM;
+
+    let mut ppu = mock(&[]);
+    ppu.put(0, 0x80).unwrap();
+    let status = |ppu: &PPU| ppu.handle(|register, _| Ok(register.status)).unwrap();
+
+    // NMI at the dot 1 of the line 241
+    ppu.exec(241 * DOTS + 1).unwrap();
+    assert!(!status(&ppu).bit(7));
+    assert!(!ppu.nmi());
+    ppu.exec(1).unwrap();
+    assert!(status(&ppu).bit(7));
+    assert!(ppu.nmi());
+
+    // PPUSTATUS read one dot before vblank
+    while !ppu.exec(1).unwrap() {}
+    ppu.exec(241 * DOTS + 1).unwrap();
+    assert_eq!(ppu.get(2).unwrap() & 0x80, 0);
+    ppu.exec(1).unwrap();
+    assert!(!status(&ppu).bit(7));
+    assert!(!ppu.nmi());
+}
+
+#[test]
+fn it_odd_frame() {
+    use crate::memory::WOM;
+
+    let mut ppu = mock(&[]);
+    let length = |ppu: &mut PPU| {
+        let mut dots = 0;
+        while !ppu.exec(1).unwrap() {
+            dots += 1;
+        }
+        dots + 1
+    };
+    assert_eq!(length(&mut ppu), DOTS * 262);
+    assert_eq!(length(&mut ppu), DOTS * 262);
+    ppu.put(1, 0x08).unwrap();
+    assert_eq!(length(&mut ppu), DOTS * 262);
+    assert_eq!(length(&mut ppu), DOTS * 262 - 1);
+    assert_eq!(ppu.cycle().frame, 4);
+}
+
+#[test]
+fn it_dendy() {
+    let mut ppu = mock(&[]).with_region(Region::Dendy);
+    let status = |ppu: &PPU| ppu.handle(|register, _| Ok(register.status)).unwrap();
+    ppu.exec(291 * DOTS + 1).unwrap();
+    assert!(!status(&ppu).bit(7));
+    ppu.exec(1).unwrap();
+    assert!(status(&ppu).bit(7));
+    // cleared at the pre-render line 311
+    ppu.exec(19 * DOTS).unwrap();
+    assert!(status(&ppu).bit(7));
+    ppu.exec(DOTS).unwrap();
+    assert_eq!(ppu.cycle().line, 311);
+    assert!(!status(&ppu).bit(7));
+}
+
+#[test]
+fn it_character_ram() {
+    use crate::memory::WOM;
+
+    // UxROM has 8KB CHR RAM
+    let mut header = crate::ines::INesHeader::new(0x8000, 0);
+    header.set_mapper(2, 0);
+    let mut data = header.to_bytes().unwrap().to_vec();
+    data.resize(16 + 0x8000, 0);
+    let ines = crate::ines::INes::parse(&data).unwrap();
+    let cartridge = crate::cartridge::load(&ines).unwrap();
+    let mut ppu = PPU::new(
+        RefCell::new(Register::default()),
+        MemoryMap::new(Rc::clone(&cartridge)),
+        Rc::new(RefCell::new(Display::default())),
+    );
+
+    // the low plane of tile 1 in the second table through PPUADDR and PPUDATA
+    ppu.put(6, 0x10).unwrap();
+    ppu.put(6, 0x10).unwrap();
+    for _ in 0..8 {
+        ppu.put(7, 0xFF).unwrap();
+    }
+    assert_eq!(cartridge.borrow().character(0x1010).unwrap(), 0xFF);
+    assert_eq!(cartridge.borrow().character(0x1018).unwrap(), 0);
+    assert_eq!(cartridge.borrow().sprite(0x1010).bits()[7], [1; 8]);
+
+    // PPUDATA reads are buffered
+    ppu.put(6, 0x10).unwrap();
+    ppu.put(6, 0x17).unwrap();
+    ppu.get(7).unwrap();
+    assert_eq!(ppu.get(7).unwrap(), 0xFF);
+    assert_eq!(ppu.get(7).unwrap(), 0);
+}
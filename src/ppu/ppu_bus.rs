@@ -12,18 +12,28 @@ impl ROM<usize> for PPU {
             1 => Err(e::writeonly(index)),
             2 => self.handle(|register, _| {
                 let status = register.status;
-                register.scroll_offset.clear();
+                register.clear_latch();
                 register.toggle_hbrank(false);
+                // read one dot before vblank
+                if self.cycle().is_vblank_start() {
+                    register.suppress_vblank();
+                }
                 Ok(status)
             }),
             3 => Err(e::writeonly(index)),
-            4 => Err(e::unimplemented()),
+            4 => self.handle(|register, _| self.oam().get(register.sprite_addr as usize)),
             5 => Err(e::writeonly(index)),
             6 => Err(e::writeonly(index)),
             7 => self.handle(|register, memory| {
-                let addr = dbg!(register.ppu_addr());
+                let addr = register.ppu_addr() as usize;
                 register.increment_ppu_addr();
-                memory.get(addr as usize)
+                // the palette is read at once, the buffer takes the nametable under it
+                if addr >= 0x3F00 {
+                    register.swap_buffer(memory.get(addr - 0x1000)?);
+                    memory.get(addr)
+                } else {
+                    Ok(register.swap_buffer(memory.get(addr)?))
+                }
             }),
             _ => Err(e::index_out_of_range(index)),
         }
@@ -35,7 +45,7 @@ impl WOM<usize> for PPU {
     fn put(&mut self, index: usize, v: u8) -> Result<()> {
         match index {
             0 => self.handle(|register, _| {
-                register.control1 = v;
+                register.put_control1(v);
                 Ok(())
             }),
             1 => self.handle(|register, _| {
@@ -47,9 +57,9 @@ impl WOM<usize> for PPU {
                 register.sprite_addr = v;
                 Ok(())
             }),
-            4 => self.handle(|register, _| {
-                register.sprite_data = v;
-                register.sprite_addr += 1;
+            4 => self.handle_oam(|register, oam| {
+                oam.put(register.sprite_addr as usize, v)?;
+                register.sprite_addr = register.sprite_addr.wrapping_add(1);
                 Ok(())
             }),
             5 => self.handle(|register, _| {
@@ -70,3 +80,38 @@ impl WOM<usize> for PPU {
         }
     }
 }
+
+#[test]
+fn it_read() {
+    let mut ppu = super::ppu::mock(&[]);
+    ppu.handle_mut(|_, memory| {
+        memory.put(0x2000, 0x11)?;
+        memory.put(0x2001, 0x22)?;
+        memory.put(0x2F00, 0x33)?;
+        memory.put(0x3F00, 0x0F)
+    })
+    .unwrap();
+
+    // PPUDATA returns the byte read before
+    ppu.put(6, 0x20).unwrap();
+    ppu.put(6, 0x00).unwrap();
+    ppu.get(7).unwrap();
+    assert_eq!(ppu.get(7).unwrap(), 0x11);
+    assert_eq!(ppu.get(7).unwrap(), 0x22);
+    // the palette is not buffered
+    ppu.put(6, 0x3F).unwrap();
+    ppu.put(6, 0x00).unwrap();
+    assert_eq!(ppu.get(7).unwrap(), 0x0F);
+    ppu.put(6, 0x20).unwrap();
+    ppu.put(6, 0x00).unwrap();
+    assert_eq!(ppu.get(7).unwrap(), 0x33);
+
+    // attribute bits 2～4 are read as 0
+    ppu.put(3, 1).unwrap();
+    ppu.put(4, 0xFF).unwrap();
+    ppu.put(4, 0xFF).unwrap();
+    ppu.put(3, 1).unwrap();
+    assert_eq!(ppu.get(4).unwrap(), 0xFF);
+    ppu.put(3, 2).unwrap();
+    assert_eq!(ppu.get(4).unwrap(), 0xE3);
+}
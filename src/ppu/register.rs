@@ -1,59 +1,177 @@
-use super::buf_byte::Bufu8;
 use crate::bits::Byte;
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+/// v and t are 15 bit addresses
+/// | yyy | NN | YYYYY | XXXXX |
+/// fine y, nametable, coarse y, coarse x
+/// https://www.nesdev.org/wiki/PPU_scrolling
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
 pub struct Register {
     pub control1: u8,
     pub control2: u8,
     pub status: u8,
+    /// OAMADDR, OAMDATA goes to the OAM of the PPU
     pub sprite_addr: u8,
-    pub sprite_data: u8,
-    /// lower, upper
-    ppu_addr: Bufu8,
-    pub scroll_offset: Bufu8,
+    /// current VRAM address
+    v: u16,
+    /// temporary VRAM address, the top left of the screen
+    t: u16,
+    /// fine x scroll
+    x: u8,
+    /// write toggle shared by PPUSCROLL and PPUADDR
+    w: bool,
+    /// PPUDATA reads return the byte read before
+    buffer: u8,
+    /// NMI requested by the rising edge of PPUCTRL bit 7 and the vblank flag
+    nmi: bool,
+    /// PPUSTATUS was read just before vblank, the flag is not set in the frame
+    vblank_suppressed: bool,
 }
 
 impl Register {
     pub fn ppu_addr(&self) -> u16 {
-        self.ppu_addr.u16_be_bytes()
+        self.v & 0x3FFF
     }
 
+    pub fn fine_x(&self) -> u8 {
+        self.x
+    }
+
+    /// PPUCTRL, the nametable goes to t.
+    /// enabling NMI in vblank raises it at once.
+    pub fn put_control1(&mut self, v: u8) {
+        if v.bit(7) && !self.control1.bit(7) && self.status.bit(7) {
+            self.nmi = true;
+        }
+        self.control1 = v;
+        self.t = (self.t & !0x0C00) | (v as u16 & 0b11) << 10;
+    }
+
+    /// PPUADDR, the upper byte then the lower byte
     pub fn put_addr(&mut self, v: u8) {
-        self.ppu_addr.add(v);
+        if self.w {
+            self.t = (self.t & 0xFF00) | v as u16;
+            self.v = self.t;
+        } else {
+            self.t = (self.t & 0x00FF) | (v as u16 & 0x3F) << 8;
+        }
+        self.w = !self.w;
+    }
+
+    /// PPUSCROLL, x then y
+    pub fn put_scroll_offset(&mut self, v: u8) {
+        if self.w {
+            let (fine, coarse) = ((v & 0b111) as u16, (v >> 3) as u16);
+            self.t = (self.t & !0x73E0) | fine << 12 | coarse << 5;
+        } else {
+            self.t = (self.t & !0x001F) | (v >> 3) as u16;
+            self.x = v & 0b111;
+        }
+        self.w = !self.w;
+    }
+
+    /// PPUSTATUS read resets the write toggle
+    pub fn clear_latch(&mut self) {
+        self.w = false;
     }
 
     pub fn toggle_hbrank(&mut self, v: bool) {
+        if v && std::mem::take(&mut self.vblank_suppressed) {
+            return;
+        }
+        if v && !self.status.bit(7) && self.control1.bit(7) {
+            self.nmi = true;
+        }
         self.status = self.status.set(7, v);
     }
 
+    /// the vblank flag and its NMI are lost
+    pub fn suppress_vblank(&mut self) {
+        self.vblank_suppressed = true;
+    }
+
+    /// takes the requested NMI
+    pub fn take_nmi(&mut self) -> bool {
+        std::mem::take(&mut self.nmi)
+    }
+
+    /// puts the read byte to the buffer and returns the previous one
+    pub fn swap_buffer(&mut self, v: u8) -> u8 {
+        std::mem::replace(&mut self.buffer, v)
+    }
+
+    pub fn toggle_sprite_zero_hit(&mut self, v: bool) {
+        self.status = self.status.set(6, v);
+    }
+
+    pub fn toggle_sprite_overflow(&mut self, v: bool) {
+        self.status = self.status.set(5, v);
+    }
+
+    /// PPUCTRL bit 2 goes across (1) or down (32)
     pub fn increment_ppu_addr(&mut self) {
-        // TODO change it by flag.
-        let addr = self.ppu_addr() + 1;
-        self.ppu_addr = Bufu8::from_16_be_bytes(addr);
+        let n = if self.control1.bit(2) { 32 } else { 1 };
+        self.v = (self.v + n) & 0x7FFF;
     }
 
-    pub fn put_scroll_offset(&mut self, v: u8) {
-        self.scroll_offset.add(v);
+    /// next tile, it wraps to the next nametable horizontally
+    pub fn increment_x(&mut self) {
+        if self.v & 0x001F == 31 {
+            self.v &= !0x001F;
+            self.v ^= 0x0400;
+        } else {
+            self.v += 1;
+        }
     }
-}
 
-impl Default for Register {
-    fn default() -> Self {
-        Self {
-            control1: 0,
-            control2: 0,
-            status: 0,
-            sprite_addr: 0,
-            sprite_data: 9,
-            ppu_addr: Bufu8::default(),
-            scroll_offset: Bufu8::default(),
+    /// next row of pixels, the coarse y wraps to the next nametable vertically at 30
+    pub fn increment_y(&mut self) {
+        if self.v & 0x7000 != 0x7000 {
+            self.v += 0x1000;
+            return;
         }
+        self.v &= !0x7000;
+        let y = match (self.v & 0x03E0) >> 5 {
+            29 => {
+                self.v ^= 0x0800;
+                0
+            }
+            // the attribute table is taken as tiles
+            31 => 0,
+            y => y + 1,
+        };
+        self.v = (self.v & !0x03E0) | y << 5;
+    }
+
+    /// the horizontal position goes back to the left of the screen
+    pub fn copy_x(&mut self) {
+        self.v = (self.v & !0x041F) | (self.t & 0x041F);
+    }
+
+    /// the vertical position goes back to the top of the screen
+    pub fn copy_y(&mut self) {
+        self.v = (self.v & !0x7BE0) | (self.t & 0x7BE0);
+    }
+
+    /// name of the tile at v
+    pub fn name_addr(&self) -> usize {
+        0x2000 | (self.v & 0x0FFF) as usize
+    }
+
+    /// attribute of the 4x4 tiles at v, and the shift of the 2x2 tiles in it
+    pub fn attribute_addr(&self) -> (usize, usize) {
+        let v = self.v as usize;
+        let addr = 0x23C0 | (v & 0x0C00) | ((v >> 4) & 0x38) | ((v >> 2) & 0x07);
+        (addr, ((v >> 4) & 0b100) | (v & 0b10))
+    }
+
+    pub fn fine_y(&self) -> usize {
+        (self.v >> 12) as usize & 0b111
     }
 }
 
 impl std::fmt::Display for Register {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "PPUADDR: {}\n", self.ppu_addr)?;
+        write!(f, "PPUADDR: 0x{:04X}\n", self.ppu_addr())?;
         write!(f, "ctr1:\n")?;
         write!(f, "NMI {} /", self.control1.bit(7))?;
         write!(f, "PPUMaster {} /", self.control1.bit(6))?;
@@ -63,3 +181,62 @@ impl std::fmt::Display for Register {
         write!(f, "PPUINC {} ", self.control1.bit(2))
     }
 }
+
+#[test]
+fn it_scroll() {
+    let mut register = Register::default();
+    register.put_control1(0b10);
+    register.put_scroll_offset(0x7D);
+    assert_eq!((register.t, register.x, register.w), (0x080F, 0b101, true));
+    register.put_scroll_offset(0x5E);
+    assert_eq!((register.t, register.w), (0x696F, false));
+    // PPUADDR shares t and the toggle
+    register.put_scroll_offset(0x00);
+    register.clear_latch();
+    register.put_addr(0x3D);
+    register.put_addr(0xF0);
+    assert_eq!(register.ppu_addr(), 0x3DF0);
+    register.copy_y();
+    assert_eq!(register.v, 0x3DF0);
+}
+
+#[test]
+fn it_increment() {
+    let mut register = Register {
+        v: 0x001F,
+        ..Default::default()
+    };
+    register.increment_x();
+    assert_eq!(register.v, 0x0400);
+    // the last row of the fine y and the coarse y
+    register.v = 0x7000 | 29 << 5;
+    register.increment_y();
+    assert_eq!(register.v, 0x0800);
+    register.v = 0x7000 | 31 << 5;
+    register.increment_y();
+    assert_eq!(register.v, 0x0000);
+    register.increment_y();
+    assert_eq!(register.v, 0x1000);
+    // PPUDATA goes down with PPUCTRL bit 2
+    register.increment_ppu_addr();
+    assert_eq!(register.v, 0x1001);
+    register.put_control1(0b100);
+    register.increment_ppu_addr();
+    assert_eq!(register.v, 0x1021);
+}
+
+#[test]
+fn it_nmi() {
+    let mut register = Register::default();
+    register.toggle_hbrank(true);
+    assert!(!register.take_nmi());
+    // enabled in vblank
+    register.put_control1(0x80);
+    assert!(register.take_nmi());
+    assert!(!register.take_nmi());
+    register.put_control1(0x80);
+    assert!(!register.take_nmi());
+    register.toggle_hbrank(false);
+    register.toggle_hbrank(true);
+    assert!(register.take_nmi());
+}
@@ -0,0 +1,78 @@
+use crate::sprite::Sprite;
+
+/// nametable page
+pub const PAGE_LENGTH: usize = 0x0400;
+
+/// memory of the nametables (0x2000～0x2FFF).
+/// the console has 2 KiB for two of them, the others are mirrors.
+/// four-screen boards add 2 KiB on the cartridge, it is kept here too.
+#[derive(Debug)]
+pub struct VRAM {
+    raw: [u8; PAGE_LENGTH * 4],
+}
+
+impl Default for VRAM {
+    fn default() -> Self {
+        Self {
+            raw: [0; PAGE_LENGTH * 4],
+        }
+    }
+}
+
+impl VRAM {
+    /// nametable slot of the address
+    /// | 0 | 1 |
+    /// | 2 | 3 |
+    pub fn slot(i: usize) -> usize {
+        (i & 0x0FFF) / PAGE_LENGTH
+    }
+
+    /// offset in the memory of the nametable address on the page
+    pub fn offset(i: usize, page: usize) -> usize {
+        page * PAGE_LENGTH + i % PAGE_LENGTH
+    }
+
+    pub fn get(&self, i: usize, page: usize) -> u8 {
+        self.raw[Self::offset(i, page)]
+    }
+
+    pub fn put(&mut self, i: usize, v: u8, page: usize) {
+        self.raw[Self::offset(i, page)] = v;
+    }
+
+    /// tile which starts at the address on the page, when the cartridge maps it as a pattern table
+    pub fn sprite(&self, i: usize, page: usize) -> Sprite {
+        let offset = Self::offset(i, page);
+        Sprite::new(self.raw[offset..offset + 16].try_into().unwrap())
+    }
+}
+
+#[test]
+fn it_offset() {
+    use crate::cartridge::Mirroring;
+
+    let offsets = |mirroring: Mirroring| {
+        [0x2000, 0x2400, 0x2800, 0x2C00, 0x3C05]
+            .map(|i| VRAM::offset(i, mirroring.page(VRAM::slot(i))))
+    };
+    assert_eq!(
+        offsets(Mirroring::Horizontal),
+        [0x0000, 0x0000, 0x0400, 0x0400, 0x0405]
+    );
+    assert_eq!(
+        offsets(Mirroring::Vertical),
+        [0x0000, 0x0400, 0x0000, 0x0400, 0x0405]
+    );
+    assert_eq!(
+        offsets(Mirroring::SingleScreenA),
+        [0x0000, 0x0000, 0x0000, 0x0000, 0x0005]
+    );
+    assert_eq!(
+        offsets(Mirroring::SingleScreenB),
+        [0x0400, 0x0400, 0x0400, 0x0400, 0x0405]
+    );
+    assert_eq!(
+        offsets(Mirroring::FourScreen),
+        [0x0000, 0x0400, 0x0800, 0x0C00, 0x0C05]
+    );
+}
@@ -0,0 +1,3 @@
+mod region;
+
+pub use region::Region;
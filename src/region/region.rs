@@ -0,0 +1,106 @@
+use crate::audio::{DENDY_CPU_CLOCK, NTSC_CPU_CLOCK, PAL_CPU_CLOCK};
+use crate::bits::Byte;
+use crate::ines::TvSystem;
+
+/// PPU dots per CPU cycle are counted in 1/5
+const DOT_UNIT: usize = 5;
+
+/// timing of the console
+/// https://www.nesdev.org/wiki/Cycle_reference_chart
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Region {
+    #[default]
+    NTSC,
+    PAL,
+    /// PAL clone, its PPU keeps the NTSC dot ratio and starts vblank late
+    Dendy,
+}
+
+impl From<TvSystem> for Region {
+    /// ROMs for both regions run as NTSC
+    fn from(v: TvSystem) -> Self {
+        match v {
+            TvSystem::PAL => Region::PAL,
+            TvSystem::Dendy => Region::Dendy,
+            TvSystem::NTSC | TvSystem::Multiple => Region::NTSC,
+        }
+    }
+}
+
+impl Region {
+    pub fn cpu_clock(&self) -> usize {
+        match self {
+            Region::NTSC => NTSC_CPU_CLOCK,
+            Region::PAL => PAL_CPU_CLOCK,
+            Region::Dendy => DENDY_CPU_CLOCK,
+        }
+    }
+
+    /// frames per second
+    pub fn frame_rate(&self) -> f64 {
+        match self {
+            Region::NTSC => 60.0988,
+            Region::PAL | Region::Dendy => 50.0070,
+        }
+    }
+
+    /// lines of a frame, the last one is the pre-render line
+    pub fn lines(&self) -> usize {
+        match self {
+            Region::NTSC => 262,
+            Region::PAL | Region::Dendy => 312,
+        }
+    }
+
+    /// line setting the vblank flag, Dendy has 51 idle lines after the picture
+    pub fn vblank_line(&self) -> usize {
+        match self {
+            Region::NTSC | Region::PAL => 241,
+            Region::Dendy => 291,
+        }
+    }
+
+    /// only NTSC PPUs skip a dot on odd frames
+    pub fn skips_dot(&self) -> bool {
+        *self == Region::NTSC
+    }
+
+    /// PPU dots of the CPU cycles, PAL runs 3.2 dots a cycle.
+    /// `remainder` keeps the fraction for the next call.
+    pub fn dots(&self, cycles: usize, remainder: &mut usize) -> usize {
+        let ratio = match self {
+            Region::PAL => 16,
+            Region::NTSC | Region::Dendy => 15,
+        };
+        let dots = cycles * ratio + *remainder;
+        *remainder = dots % DOT_UNIT;
+        dots / DOT_UNIT
+    }
+
+    /// PPUMASK as the PPU sees it, PAL PPUs swap the red and green emphasis
+    pub fn mask(&self, v: u8) -> u8 {
+        match self {
+            Region::NTSC => v,
+            Region::PAL | Region::Dendy => v.set(5, v.bit(6)).set(6, v.bit(5)),
+        }
+    }
+}
+
+#[test]
+fn it_dots() {
+    let mut remainder = 0;
+    assert_eq!(Region::NTSC.dots(7, &mut remainder), 21);
+    assert_eq!(remainder, 0);
+    let dots = (0..5)
+        .map(|_| Region::PAL.dots(1, &mut remainder))
+        .collect::<Vec<_>>();
+    assert_eq!(dots, vec![3, 3, 3, 3, 4]);
+    assert_eq!(remainder, 0);
+}
+
+#[test]
+fn it_mask() {
+    assert_eq!(Region::NTSC.mask(0x3E), 0x3E);
+    assert_eq!(Region::PAL.mask(0x3E), 0x5E);
+    assert_eq!(Region::Dendy.mask(0xDE), 0xBE);
+}
@@ -0,0 +1,3 @@
+mod rom;
+
+pub use rom::{run, Command};
@@ -0,0 +1,249 @@
+use crate::cartridge::Mirroring;
+use crate::ines::{INes, INesHeader};
+use crate::result::Result;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum Arrangement {
+    Horizontal,
+    Vertical,
+    FourScreen,
+}
+
+impl From<Arrangement> for Mirroring {
+    fn from(v: Arrangement) -> Self {
+        match v {
+            Arrangement::Horizontal => Mirroring::Horizontal,
+            Arrangement::Vertical => Mirroring::Vertical,
+            Arrangement::FourScreen => Mirroring::FourScreen,
+        }
+    }
+}
+
+/// values written over the header
+#[derive(clap::Args, Debug)]
+pub struct Overrides {
+    #[arg(long)]
+    mapper: Option<u16>,
+
+    #[arg(long)]
+    submapper: Option<u8>,
+
+    #[arg(long, value_enum)]
+    mirroring: Option<Arrangement>,
+
+    #[arg(long)]
+    battery: Option<bool>,
+}
+
+impl Overrides {
+    fn apply(&self, header: &mut INesHeader) {
+        if self.mapper.is_some() || self.submapper.is_some() {
+            header.set_mapper(
+                self.mapper.unwrap_or(header.mapper()),
+                self.submapper.unwrap_or(header.submapper()),
+            );
+        }
+        if let Some(mirroring) = self.mirroring {
+            header.set_mirroring(mirroring.into());
+        }
+        if let Some(battery) = self.battery {
+            header.set_battery(battery);
+        }
+    }
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum Command {
+    /// rewrite the header, junk in bytes 7-15 is cleared
+    FixHeader {
+        input: PathBuf,
+
+        /// overwrite the input by default
+        #[arg(short)]
+        output: Option<PathBuf>,
+
+        #[command(flatten)]
+        overrides: Overrides,
+    },
+
+    /// convert an iNES file to NES 2.0
+    Convert {
+        input: PathBuf,
+
+        #[arg(short)]
+        output: Option<PathBuf>,
+    },
+
+    /// write PRG ROM and CHR ROM to separate files
+    Split {
+        input: PathBuf,
+
+        #[arg(long)]
+        prg: PathBuf,
+
+        #[arg(long)]
+        chr: Option<PathBuf>,
+    },
+
+    /// build a .nes file from PRG ROM and CHR ROM
+    Join {
+        #[arg(long)]
+        prg: PathBuf,
+
+        /// the board uses CHR RAM when omitted
+        #[arg(long)]
+        chr: Option<PathBuf>,
+
+        #[arg(short)]
+        output: PathBuf,
+
+        /// write NES 2.0 header
+        #[arg(long)]
+        nes2: bool,
+
+        #[command(flatten)]
+        overrides: Overrides,
+    },
+}
+
+pub fn run(command: &Command) -> Result<()> {
+    match command {
+        Command::FixHeader {
+            input,
+            output,
+            overrides,
+        } => {
+            let data = fs::read(input)?;
+            let ines = INes::parse(&data)?;
+            let mut header = *ines.header();
+            overrides.apply(&mut header);
+            write(output.as_deref().unwrap_or(input), &header, ines.body())
+        }
+        Command::Convert { input, output } => {
+            let data = fs::read(input)?;
+            let ines = INes::parse(&data)?;
+            let header = ines.header().to_nes2();
+            write(output.as_deref().unwrap_or(input), &header, ines.body())
+        }
+        Command::Split { input, prg, chr } => {
+            let data = fs::read(input)?;
+            let ines = INes::parse(&data)?;
+            fs::write(prg, ines.program())?;
+            if let Some(chr) = chr {
+                fs::write(chr, ines.character())?;
+            }
+            Ok(())
+        }
+        Command::Join {
+            prg,
+            chr,
+            output,
+            nes2,
+            overrides,
+        } => {
+            let program = fs::read(prg)?;
+            let character = match chr {
+                Some(chr) => fs::read(chr)?,
+                None => vec![],
+            };
+            let mut header = INesHeader::new(program.len(), character.len());
+            overrides.apply(&mut header);
+            if *nes2 {
+                header = header.to_nes2();
+            }
+            write(output, &header, &[program, character].concat())
+        }
+    }
+}
+
+/// the file is written to a temporary file and renamed,
+/// so the input is kept as it is when it is overwritten and the write fails.
+fn write(path: &Path, header: &INesHeader, body: &[u8]) -> Result<()> {
+    let data = [&header.to_bytes()?[..], body].concat();
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    let mut f = fs::File::create(&tmp)?;
+    f.write_all(&data)?;
+    f.sync_all()?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+#[test]
+fn it_join_and_split() {
+    let dir = std::env::temp_dir().join(format!("fc-rom-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let program = (0..0x8000).map(|i| i as u8).collect::<Vec<u8>>();
+    let character = (0..0x2000).map(|i| (i >> 8) as u8).collect::<Vec<u8>>();
+    fs::write(dir.join("prg.bin"), &program).unwrap();
+    fs::write(dir.join("chr.bin"), &character).unwrap();
+
+    run(&Command::Join {
+        prg: dir.join("prg.bin"),
+        chr: Some(dir.join("chr.bin")),
+        output: dir.join("game.nes"),
+        nes2: false,
+        overrides: Overrides {
+            mapper: Some(3),
+            submapper: None,
+            mirroring: Some(Arrangement::Vertical),
+            battery: None,
+        },
+    })
+    .unwrap();
+    let data = fs::read(dir.join("game.nes")).unwrap();
+    let ines = INes::parse(&data).unwrap();
+    assert_eq!(ines.header().mapper(), 3);
+    assert_eq!(ines.header().mirroring(), Mirroring::Vertical);
+
+    run(&Command::Convert {
+        input: dir.join("game.nes"),
+        output: None,
+    })
+    .unwrap();
+    run(&Command::Split {
+        input: dir.join("game.nes"),
+        prg: dir.join("prg2.bin"),
+        chr: Some(dir.join("chr2.bin")),
+    })
+    .unwrap();
+    assert_eq!(fs::read(dir.join("prg2.bin")).unwrap(), program);
+    assert_eq!(fs::read(dir.join("chr2.bin")).unwrap(), character);
+    let data = fs::read(dir.join("game.nes")).unwrap();
+    assert!(INes::parse(&data).unwrap().header().nes2());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn it_fix_header() {
+    let dir = std::env::temp_dir().join(format!("fc-fix-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let mut data = vec![0x4E, 0x45, 0x53, 0x1A, 1, 0, 0x10];
+    data.extend_from_slice(b"DiskDude!");
+    data.extend_from_slice(&[0xEA; 0x4000]);
+    fs::write(dir.join("game.nes"), &data).unwrap();
+
+    run(&Command::FixHeader {
+        input: dir.join("game.nes"),
+        output: None,
+        overrides: Overrides {
+            mapper: None,
+            submapper: None,
+            mirroring: None,
+            battery: Some(true),
+        },
+    })
+    .unwrap();
+    let fixed = fs::read(dir.join("game.nes")).unwrap();
+    assert_eq!(
+        fixed[..16],
+        [0x4E, 0x45, 0x53, 0x1A, 1, 0, 0x12, 0, 0, 0, 0, 0, 0, 0, 0, 0]
+    );
+    assert_eq!(fixed[16..], data[16..]);
+    assert!(!dir.join("game.nes.tmp").exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,3 @@
+mod save;
+
+pub use save::SaveFile;
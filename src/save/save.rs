@@ -0,0 +1,100 @@
+use crate::result::Result;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// .sav file of the battery-backed memory
+#[derive(Debug)]
+pub struct SaveFile {
+    path: PathBuf,
+    /// the data last loaded or written, to skip writing the same data
+    last: Option<Vec<u8>>,
+}
+
+impl SaveFile {
+    /// `<dir>/<rom name>.sav`, or next to the ROM when `dir` is None
+    pub fn new(rom: &Path, dir: Option<&Path>) -> Self {
+        let name = rom.with_extension("sav");
+        let path = match (dir, name.file_name()) {
+            (Some(dir), Some(file)) => dir.join(file),
+            _ => name,
+        };
+        Self { path, last: None }
+    }
+
+    /// the same file name with another extension
+    pub fn with_extension(mut self, extension: &str) -> Self {
+        self.path.set_extension(extension);
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// None when nothing has been saved yet
+    pub fn load(&mut self) -> Result<Option<Vec<u8>>> {
+        match fs::read(&self.path) {
+            Ok(data) => {
+                self.last = Some(data.clone());
+                Ok(Some(data))
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// the data is written to a temporary file and renamed,
+    /// so the old file is kept if the emulator dies while writing.
+    pub fn flush(&mut self, data: &[u8]) -> Result<()> {
+        if self.last.as_deref() == Some(data) {
+            return Ok(());
+        }
+        if let Some(dir) = self.path.parent() {
+            if !dir.as_os_str().is_empty() {
+                fs::create_dir_all(dir)?;
+            }
+        }
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        let mut f = fs::File::create(&tmp)?;
+        f.write_all(data)?;
+        f.sync_all()?;
+        fs::rename(&tmp, &self.path)?;
+        self.last = Some(data.to_vec());
+        Ok(())
+    }
+}
+
+#[test]
+fn it_path() {
+    let save = SaveFile::new(Path::new("roms/zelda.nes"), None);
+    assert_eq!(save.path(), Path::new("roms/zelda.sav"));
+    let save = SaveFile::new(Path::new("roms/zelda.nes"), Some(Path::new("saves")));
+    assert_eq!(save.path(), Path::new("saves/zelda.sav"));
+}
+
+#[test]
+fn it_flush_and_load() {
+    let dir = std::env::temp_dir().join(format!("fc-save-{}", std::process::id()));
+    let mut save = SaveFile::new(Path::new("game.nes"), Some(&dir));
+    assert_eq!(save.load().unwrap(), None);
+    save.flush(&[1, 2, 3]).unwrap();
+    save.flush(&[4, 5, 6]).unwrap();
+    let mut other = SaveFile::new(Path::new("game.nes"), Some(&dir));
+    assert_eq!(other.load().unwrap(), Some(vec![4, 5, 6]));
+    assert!(!dir.join("game.sav.tmp").exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn it_flush_other_extension() {
+    let dir = std::env::temp_dir().join(format!("fc-save-ips-{}", std::process::id()));
+    let mut save = SaveFile::new(Path::new("disk.fds"), Some(&dir)).with_extension("ips");
+    save.flush(&[1, 2, 3]).unwrap();
+    assert_eq!(fs::read(dir.join("disk.ips")).unwrap(), [1, 2, 3]);
+    assert!(!dir.join("disk.ips.tmp").exists());
+    assert!(!dir.join("disk.sav.tmp").exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
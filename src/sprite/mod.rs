@@ -5,6 +5,7 @@ pub use crate::ines::SpriteROM;
 pub use sprite::Sprite;
 pub use sprite_byte::SpriteByte;
 
+#[cfg(debug_assertions)]
 pub fn debug_sprite(sprites: SpriteROM) {
     let mut image: image::RgbImage = image::ImageBuffer::new(256 * 2, 240 * 2);
     let sprite_per_line = 256 / 8;
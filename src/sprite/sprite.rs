@@ -12,7 +12,8 @@ impl Sprite {
         let a = value[0..8].iter().map(|v| SpriteByte::from(v.clone()));
         let b = value[8..16].iter().map(|v| SpriteByte::from(v.clone()));
         let sprites = std::iter::zip(a, b)
-            .map(|(v1, v2)| v1 + v2)
+            // the second plane is the upper bit of the color
+            .map(|(v1, v2)| v1 + v2 + v2)
             .map(|s| <[u8; 8]>::from(s))
             .collect::<Vec<[u8; 8]>>();
         Sprite {
@@ -32,8 +33,23 @@ impl Sprite {
         self.raw
     }
 
+    /// the tile with every row replaced by the row y
+    pub fn line(&self, y: usize) -> Self {
+        Sprite {
+            raw: [self.raw[y]; SPRITE_LENGTH],
+        }
+    }
+
     #[cfg(test)]
     pub fn debug_new(raw: [[u8; SPRITE_LENGTH]; SPRITE_LENGTH]) -> Self {
         Self { raw }
     }
 }
+
+#[test]
+fn it_new() {
+    let mut value = [0; SPRITE_LENGTH * 2];
+    value[0] = 0b1010_0000;
+    value[8] = 0b1100_0000;
+    assert_eq!(Sprite::new(&value).bits()[0], [3, 2, 1, 0, 0, 0, 0, 0]);
+}
@@ -0,0 +1,3 @@
+mod unif;
+
+pub use unif::{load, Unif};
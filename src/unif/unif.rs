@@ -0,0 +1,308 @@
+use crate::cartridge::Mirroring;
+use crate::ines::{INesHeader, TvSystem};
+use crate::result::Result;
+
+/// b"UNIF"
+const MAGIC: [u8; 4] = [0x55, 0x4E, 0x49, 0x46];
+/// magic, revision and zero padding
+const HEADER_LENGTH: usize = 32;
+/// ID and length of a chunk
+const CHUNK_HEADER_LENGTH: usize = 8;
+
+/// UNIF board names without the NES-/HVC- prefix, and the iNES mapper of the board.
+/// only the boards of the implemented mappers, the others are reported by name.
+const BOARDS: &[(&str, u16)] = &[
+    ("NROM", 0),
+    ("NROM-128", 0),
+    ("NROM-256", 0),
+    ("UNROM", 2),
+    ("UOROM", 2),
+    ("EKROM", 5),
+    ("ELROM", 5),
+    ("ETROM", 5),
+    ("EWROM", 5),
+    ("PNROM", 9),
+    ("PEEOROM", 9),
+    ("FJROM", 10),
+    ("FKROM", 10),
+    ("JLROM", 69),
+    ("JSROM", 69),
+    ("BTR", 69),
+];
+
+/// UNIF (.unf) file, chunks of board name and ROM data
+#[derive(Debug)]
+pub struct Unif {
+    /// MAPR
+    board: String,
+    /// NAME
+    name: Option<String>,
+    /// PRG0～PRGF in order
+    program: Vec<u8>,
+    /// CHR0～CHRF in order, empty for CHR RAM
+    character: Vec<u8>,
+    /// MIRR, None when the mapper controls it
+    mirroring: Option<Mirroring>,
+    /// BATR
+    battery: bool,
+    /// TVCI
+    tv_system: Option<TvSystem>,
+    /// CTRL bit 0: standard, 1: zapper, 2: R.O.B., 3: arkanoid, 4: power pad, 5: four score
+    controllers: u8,
+}
+
+/// the file as an iNES image, UNIF is told by the content and converted
+pub fn load(data: Vec<u8>) -> Result<Vec<u8>> {
+    if Unif::is_unif(&data) {
+        Unif::parse(&data)?.to_ines()
+    } else {
+        Ok(data)
+    }
+}
+
+/// text chunks are terminated by 0
+fn text(data: &[u8]) -> String {
+    let end = data.iter().position(|&v| v == 0).unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end]).trim().to_string()
+}
+
+impl Unif {
+    pub fn is_unif(data: &[u8]) -> bool {
+        data.starts_with(&MAGIC)
+    }
+
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        if !Self::is_unif(data) {
+            return Err(anyhow::anyhow!("not UNIF format, bad magic"));
+        }
+        if data.len() < HEADER_LENGTH {
+            return Err(anyhow::anyhow!(
+                "not UNIF format, the header needs {} bytes but the file has {} bytes",
+                HEADER_LENGTH,
+                data.len()
+            ));
+        }
+
+        let mut board = None;
+        let mut name = None;
+        let mut programs: [&[u8]; 16] = [&[]; 16];
+        let mut characters: [&[u8]; 16] = [&[]; 16];
+        let mut mirroring = None;
+        let mut battery = false;
+        let mut tv_system = None;
+        let mut controllers = 0;
+
+        let mut offset = HEADER_LENGTH;
+        while offset < data.len() {
+            let left = data.len() - offset;
+            if left < CHUNK_HEADER_LENGTH {
+                return Err(anyhow::anyhow!(
+                    "truncated file, {} bytes are left at offset {}",
+                    left,
+                    offset
+                ));
+            }
+            let id = &data[offset..offset + 4];
+            let length = u32::from_le_bytes(data[offset + 4..offset + 8].try_into()?) as usize;
+            offset += CHUNK_HEADER_LENGTH;
+            if length > data.len() - offset {
+                return Err(anyhow::anyhow!(
+                    "truncated file, chunk {} needs {} bytes at offset {} but {} bytes are left",
+                    String::from_utf8_lossy(id),
+                    length,
+                    offset,
+                    data.len() - offset
+                ));
+            }
+            let chunk = &data[offset..offset + length];
+            offset += length;
+
+            // the last character of PRGn / CHRn is a hex digit
+            let bank = || (id[3] as char).to_digit(16).map(|n| n as usize);
+            match id {
+                b"MAPR" => board = Some(text(chunk)),
+                b"NAME" => name = Some(text(chunk)),
+                b"MIRR" => {
+                    mirroring = match chunk.first() {
+                        Some(0) => Some(Mirroring::Horizontal),
+                        Some(1) => Some(Mirroring::Vertical),
+                        Some(2) => Some(Mirroring::SingleScreenA),
+                        Some(3) => Some(Mirroring::SingleScreenB),
+                        Some(4) => Some(Mirroring::FourScreen),
+                        _ => None,
+                    }
+                }
+                b"BATR" => battery = chunk.first().is_none_or(|&v| v != 0),
+                b"TVCI" => {
+                    tv_system = match chunk.first() {
+                        Some(0) => Some(TvSystem::NTSC),
+                        Some(1) => Some(TvSystem::PAL),
+                        Some(2) => Some(TvSystem::Multiple),
+                        _ => None,
+                    }
+                }
+                b"CTRL" => controllers = chunk.first().copied().unwrap_or(0),
+                [b'P', b'R', b'G', _] if bank().is_some() => programs[bank().unwrap()] = chunk,
+                [b'C', b'H', b'R', _] if bank().is_some() => characters[bank().unwrap()] = chunk,
+                // READ, DINF, PCKn, CCKn and others are not needed to run
+                _ => {}
+            }
+        }
+
+        let program = programs.concat();
+        if program.is_empty() {
+            return Err(anyhow::anyhow!("no PRG chunk"));
+        }
+        Ok(Self {
+            board: board.ok_or_else(|| anyhow::anyhow!("no MAPR chunk"))?,
+            name: name.filter(|v| !v.is_empty()),
+            program,
+            character: characters.concat(),
+            mirroring,
+            battery,
+            tv_system,
+            controllers,
+        })
+    }
+
+    pub fn board(&self) -> &str {
+        &self.board
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// None for unknown boards
+    pub fn mapper(&self) -> Option<u16> {
+        let board = ["NES-", "HVC-"]
+            .iter()
+            .find_map(|prefix| self.board.strip_prefix(prefix))
+            .unwrap_or(&self.board);
+        BOARDS
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(board))
+            .map(|&(_, mapper)| mapper)
+    }
+
+    /// NES 2.0 expansion device of the controllers, special ones first
+    fn expansion_device(&self) -> u8 {
+        match self.controllers {
+            v if v & 0b0000_1000 != 0 => 0x0F,
+            v if v & 0b0001_0000 != 0 => 0x0B,
+            v if v & 0b0000_0010 != 0 => 0x08,
+            v if v & 0b0010_0000 != 0 => 0x02,
+            v if v & 0b0000_0001 != 0 => 0x01,
+            _ => 0x00,
+        }
+    }
+
+    /// NES 2.0 header with the same board, sizes of UNIF are not limited to 16 KB units
+    pub fn header(&self) -> Result<INesHeader> {
+        let mapper = self
+            .mapper()
+            .ok_or_else(|| anyhow::anyhow!("unsupported UNIF board {}", self.board))?;
+        let mut header = INesHeader::new(self.program.len(), self.character.len());
+        header.set_mapper(mapper, 0);
+        // single screen boards switch the screens themselves
+        header.set_mirroring(match self.mirroring {
+            Some(m @ (Mirroring::Vertical | Mirroring::FourScreen)) => m,
+            _ => Mirroring::Horizontal,
+        });
+        header.set_battery(self.battery);
+        if let Some(tv_system) = self.tv_system {
+            header.set_tv_system(tv_system);
+        }
+        header.set_expansion_device(self.expansion_device());
+        Ok(header.to_nes2())
+    }
+
+    /// header, PRG ROM and CHR ROM, read as any other .nes file
+    pub fn to_ines(&self) -> Result<Vec<u8>> {
+        let mut data = self.header()?.to_bytes()?.to_vec();
+        data.extend_from_slice(&self.program);
+        data.extend_from_slice(&self.character);
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+fn chunk(id: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut v = id.to_vec();
+    v.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    v.extend_from_slice(data);
+    v
+}
+
+#[cfg(test)]
+fn mock() -> Vec<u8> {
+    let mut data = MAGIC.to_vec();
+    data.extend_from_slice(&7u32.to_le_bytes());
+    data.resize(HEADER_LENGTH, 0);
+    data.extend(chunk(b"MAPR", b"NES-NROM-256\0"));
+    data.extend(chunk(b"NAME", b"Test\0"));
+    data.extend(chunk(b"PRG1", &[1; 0x4000]));
+    data.extend(chunk(b"PRG0", &[0; 0x4000]));
+    data.extend(chunk(b"CHR0", &[2; 0x2000]));
+    data.extend(chunk(b"MIRR", &[1]));
+    data.extend(chunk(b"BATR", &[1]));
+    data.extend(chunk(b"TVCI", &[1]));
+    data.extend(chunk(b"CTRL", &[0b11]));
+    data
+}
+
+#[test]
+fn it_parse() {
+    let unif = Unif::parse(&mock()).unwrap();
+    assert_eq!(unif.board(), "NES-NROM-256");
+    assert_eq!(unif.name(), Some("Test"));
+    assert_eq!(unif.mapper(), Some(0));
+    assert_eq!(unif.program.len(), 0x8000);
+    assert_eq!(unif.program[0x3FFF..0x4001], [0, 1]);
+    assert_eq!(unif.expansion_device(), 0x08);
+}
+
+#[test]
+fn it_load() {
+    let data = load(mock()).unwrap();
+    let ines = crate::ines::INes::parse(&data).unwrap();
+    let header = ines.header();
+    assert_eq!(header.mapper(), 0);
+    assert_eq!(header.mirroring(), Mirroring::Vertical);
+    assert!(header.battery());
+    assert_eq!(header.tv_system(), TvSystem::PAL);
+    assert_eq!(ines.program()[0x4000], 1);
+    assert_eq!(ines.character()[0], 2);
+
+    // iNES files are passed through
+    assert_eq!(load(data.clone()).unwrap(), data);
+}
+
+#[test]
+fn it_parse_errors() {
+    let mut data = mock();
+    data.truncate(data.len() - 1);
+    let message = Unif::parse(&data).unwrap_err().to_string();
+    assert!(message.contains("chunk CTRL needs 1 bytes"), "{}", message);
+
+    let mut data = mock();
+    data[HEADER_LENGTH + CHUNK_HEADER_LENGTH..HEADER_LENGTH + CHUNK_HEADER_LENGTH + 3]
+        .copy_from_slice(b"XYZ");
+    let message = Unif::parse(&data)
+        .unwrap()
+        .header()
+        .unwrap_err()
+        .to_string();
+    assert!(message.contains("unsupported UNIF board"), "{}", message);
+
+    // MMC1 is not implemented, its boards are reported instead of converted
+    let mut data = mock();
+    data[HEADER_LENGTH + CHUNK_HEADER_LENGTH..HEADER_LENGTH + CHUNK_HEADER_LENGTH + 12]
+        .copy_from_slice(b"NES-SLROM\0\0\0");
+    let message = load(data).unwrap_err().to_string();
+    assert!(
+        message.contains("unsupported UNIF board NES-SLROM"),
+        "{}",
+        message
+    );
+}